@@ -0,0 +1,186 @@
+use std::hash::{DefaultHasher, Hash, Hasher};
+use crate::{
+	cache::Admitter,
+	list::List
+};
+
+const SKETCH_DEPTH: usize = 4;
+const COUNTER_MAXIMUM: u8 = 15;
+
+fn hash(key: &str) -> (u64, u64) {
+	let mut hasher: DefaultHasher = DefaultHasher::new();
+
+	key.hash(&mut hasher);
+
+	let value: u64 = hasher.finish();
+
+	// double hashing derives any number of indices from two halves
+	(value & 0xFFFFFFFF, (value >> 32) | 1)
+}
+
+struct CountMinSketch {
+	counters: Vec<u8>,
+	width: usize
+}
+
+impl CountMinSketch {
+	fn new(width: usize) -> Self {
+		CountMinSketch {
+			counters: vec![0; width * SKETCH_DEPTH],
+			width: width
+		}
+	}
+
+	fn index(self: &Self, hashes: (u64, u64), row: usize) -> usize {
+		row * self.width + (hashes.0.wrapping_add(hashes.1.wrapping_mul(row as u64 + 1)) as usize & (self.width - 1))
+	}
+
+	fn increment(self: &mut Self, hashes: (u64, u64)) {
+		for row in 0..SKETCH_DEPTH {
+			let index: usize = self.index(hashes, row);
+
+			if self.counters[index] < COUNTER_MAXIMUM {
+				self.counters[index] += 1;
+			}
+		}
+	}
+
+	fn estimate(self: &Self, hashes: (u64, u64)) -> u8 {
+		(0..SKETCH_DEPTH).map(|row: usize| self.counters[self.index(hashes, row)])
+			.min()
+			.unwrap_or(0)
+	}
+
+	fn halve(self: &mut Self) {
+		for counter in &mut self.counters {
+			*counter >>= 1;
+		}
+	}
+}
+
+// filters keys seen once in current window so sketch only counts repeated keys
+struct Doorkeeper {
+	bits: Vec<u64>,
+	length: usize
+}
+
+impl Doorkeeper {
+	fn new(length: usize) -> Self {
+		Doorkeeper {
+			bits: vec![0; length.div_ceil(64)],
+			length: length
+		}
+	}
+
+	fn positions(self: &Self, hashes: (u64, u64)) -> [usize; 2] {
+		[
+			hashes.1 as usize & (self.length - 1),
+			hashes.0.wrapping_add(hashes.1.rotate_left(16)) as usize & (self.length - 1)
+		]
+	}
+
+	fn contains(self: &Self, hashes: (u64, u64)) -> bool {
+		self.positions(hashes)
+			.iter()
+			.all(|position: &usize| self.bits[position / 64] & 1 << (position % 64) != 0)
+	}
+
+	// returns whether key was already present
+	fn insert(self: &mut Self, hashes: (u64, u64)) -> bool {
+		let mut is_present: bool = true;
+
+		for position in self.positions(hashes) {
+			if self.bits[position / 64] & 1 << (position % 64) == 0 {
+				is_present = false;
+				self.bits[position / 64] |= 1 << (position % 64);
+			}
+		}
+
+		is_present
+	}
+
+	fn clear(self: &mut Self) {
+		self.bits.fill(0);
+	}
+}
+
+// W-TinyLFU, new keys always enter small recency window and only key leaving window competes against main victim
+pub struct WindowTinyLeastFrequentlyUsed {
+	sketch: CountMinSketch,
+	doorkeeper: Doorkeeper,
+	sample_count: usize,
+	// samples between halvings of sketch
+	reset_period: usize,
+	window: List,
+	window_size: usize
+}
+
+impl WindowTinyLeastFrequentlyUsed {
+	pub fn new(capacity: usize) -> Self {
+		let width: usize = capacity.max(16).next_power_of_two();
+
+		WindowTinyLeastFrequentlyUsed {
+			sketch: CountMinSketch::new(width),
+			doorkeeper: Doorkeeper::new(width * 8),
+			sample_count: 0,
+			reset_period: capacity.max(16) * 10,
+			window: List::new(),
+			// 1% of capacity as in W-TinyLFU paper
+			window_size: (capacity / 100).max(1)
+		}
+	}
+
+	fn estimate(self: &Self, key: &str) -> u8 {
+		let hashes: (u64, u64) = hash(key);
+
+		self.sketch.estimate(hashes) + self.doorkeeper.contains(hashes) as u8
+	}
+}
+
+impl Admitter for WindowTinyLeastFrequentlyUsed {
+	fn record(self: &mut Self, key: &str) {
+		let hashes: (u64, u64) = hash(key);
+
+		if self.doorkeeper.insert(hashes) {
+			self.sketch.increment(hashes);
+		}
+
+		self.sample_count += 1;
+
+		// ages frequencies once per period so old popularity fades
+		if self.sample_count >= self.reset_period {
+			self.sketch.halve();
+			self.doorkeeper.clear();
+			self.sample_count /= 2;
+		}
+	}
+
+	// candidate itself is never refused, so recency bursts get their window time
+	fn select_evictee(self: &mut Self, _candidate_key: &str, victim_key: &str) -> Option<String> {
+		if self.window.len() < self.window_size {
+			return Some(victim_key.to_owned());
+		}
+
+		// key leaving window joins main space only if more frequent than its victim
+		let window_key: String = self.window.pop_back()?;
+
+		if self.estimate(&window_key) > self.estimate(victim_key) {
+			Some(victim_key.to_owned())
+		} else {
+			Some(window_key)
+		}
+	}
+
+	fn on_insert(self: &mut Self, key: &str) {
+		self.window.push_front(key);
+
+		// keys inserted without contest, such as client writes, graduate from window unchallenged
+		while self.window.len() > self.window_size {
+			self.window.pop_back();
+		}
+	}
+
+	fn on_remove(self: &mut Self, key: &str) {
+		self.window.remove(key);
+	}
+}
\ No newline at end of file
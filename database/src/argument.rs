@@ -11,15 +11,45 @@ use std::{
 	process::exit
 };
 use crate::{
-	cache::Model,
-	common::Result,
+	cache::{Admission, Aging, Model},
+	common::{Result, parse_size},
+	network::Backend,
 	protocol::Version
 };
 
 pub struct Argument {
 	pub model: Model,
+	pub is_automatic: bool,
+	pub automatic_models: Vec<Model>,
+	pub automatic_window: u64,
+	pub automatic_margin: f64,
+	pub model_path: Option<String>,
+	pub model_directory: Option<String>,
+	pub backend: Backend,
+	pub fallback: Option<Model>,
+	pub shadows: Vec<(Model, Option<usize>)>,
+	pub inference_budget: Option<u64>,
+	pub fallback_cooldown: u64,
+	pub lfu_aging: Option<Aging>,
+	pub admission: Option<Admission>,
+	pub eviction_samples: usize,
+	pub eviction_pool: usize,
+	pub time_scale: u64,
+	pub is_online_trained: bool,
+	pub training_interval: u64,
+	pub lecar_learning_rate: f64,
+	pub lecar_discount: Option<f64>,
 	pub capacity: usize,
+	pub maximum_memory: Option<usize>,
+	pub low_watermark: f64,
+	pub is_background_evicted: bool,
+	pub replay: Option<String>,
+	pub eviction_interval: u64,
 	pub directory: String,
+	pub sweep_interval: u64,
+	pub is_experience_logged: bool,
+	pub experience_rows: usize,
+	pub experience_files: usize,
 	pub host: Ipv4Addr,
 	pub port: u16,
 	pub is_verbose: bool,
@@ -29,14 +59,48 @@ pub struct Argument {
 
 impl Argument {
 	pub fn new() -> Result<Self> {
+		#[cfg(feature = "onnxruntime")]
+		let backend: Backend = Backend::OnnxRuntime;
+		#[cfg(not(feature = "onnxruntime"))]
+		let backend: Backend = Backend::Rust;
+
 		let mut argument: Argument = Argument {
 			model: Model::DeepQNetwork,
+			is_automatic: false,
+			automatic_models: vec![Model::DeepQNetwork, Model::LeastRecentlyUsed, Model::LeastFrequentlyUsed],
+			automatic_window: 10000,
+			automatic_margin: 0.02,
+			model_path: None,
+			model_directory: None,
+			backend: backend,
+			fallback: Some(Model::LeastRecentlyUsed),
+			shadows: Vec::new(),
+			inference_budget: None,
+			fallback_cooldown: 60,
+			lfu_aging: None,
+			admission: None,
+			eviction_samples: 0,
+			eviction_pool: 0,
+			time_scale: 1,
+			is_online_trained: false,
+			training_interval: 300,
+			lecar_learning_rate: 0.45,
+			lecar_discount: None,
 			capacity: 128,
+			maximum_memory: None,
+			low_watermark: 1.0,
+			is_background_evicted: false,
+			replay: None,
+			eviction_interval: 10,
 			directory: (if cfg!(target_os = "windows") {
 				".\\data"
 			} else {
 				"./data"
 			}).to_string(),
+			sweep_interval: 1,
+			is_experience_logged: false,
+			experience_rows: 1 << 20,
+			experience_files: 8,
 			host: Ipv4Addr::new(127, 0, 0, 1),
 			port: 5190,
 			is_verbose: false,
@@ -74,15 +138,207 @@ impl Argument {
 		while let Some(value) = arguments.next() {
 			match value.as_str() {
 				"--model" | "-m" => if let Some(raw_model) = arguments.next() {
-					match raw_model.to_ascii_lowercase().as_str() {
-						"dqn" | "deepqnetwork" => argument.model = Model::DeepQNetwork,
-						"lru" | "leastrecentlyused" => argument.model = Model::LeastRecentlyUsed,
-						"lfu" | "leastfrequentlyused" => argument.model = Model::LeastFrequentlyUsed,
-						_ => return Err(Box::from("model must be one of dqn, lru, lfu"))
+					if raw_model.eq_ignore_ascii_case("auto") {
+						argument.is_automatic = true;
+					} else {
+						argument.is_automatic = false;
+						argument.model = Model::try_from(raw_model.as_str())?;
 					}
 				} else {
 					return Err(Box::from("model must be provided"));
 				}
+				"--auto-models" => if let Some(raw_automatic_models) = arguments.next() {
+					argument.automatic_models = raw_automatic_models.split(',')
+						.map(|raw_model: &str| Model::try_from(raw_model.trim()))
+						.collect::<Result<Vec<Model>>>()?;
+
+					if argument.automatic_models.len() < 2 {
+						return Err(Box::from("auto models must have at least 2 models"));
+					}
+
+					// experts are looked up by model when switching
+					if argument.automatic_models.iter().enumerate().any(|(i, model): (usize, &Model)| argument.automatic_models[..i].contains(model)) {
+						return Err(Box::from("auto models must be distinct"));
+					}
+				} else {
+					return Err(Box::from("auto models must be provided"));
+				},
+				"--auto-window" => if let Some(raw_automatic_window) = arguments.next() {
+					argument.automatic_window = raw_automatic_window.parse::<u64>()?;
+
+					if argument.automatic_window == 0 {
+						return Err(Box::from("auto window must be greater than 0"));
+					}
+				} else {
+					return Err(Box::from("auto window must be provided"));
+				},
+				"--auto-margin" => if let Some(raw_automatic_margin) = arguments.next() {
+					argument.automatic_margin = raw_automatic_margin.parse::<f64>()?;
+
+					if !(argument.automatic_margin >= 0.0 && argument.automatic_margin < 1.0) {
+						return Err(Box::from("auto margin must be greater than or equal to 0 and less than 1"));
+					}
+				} else {
+					return Err(Box::from("auto margin must be provided"));
+				},
+				"--model-path" => if let Some(model_path) = arguments.next() {
+					if !metadata(&model_path)?.is_file() {
+						return Err(Box::from("model path must be file"));
+					}
+
+					argument.model_path = Some(model_path);
+				} else {
+					return Err(Box::from("model path must be provided"));
+				},
+				"--model-directory" => if let Some(model_directory) = arguments.next() {
+					if !metadata(&model_directory)?.is_dir() {
+						return Err(Box::from("model directory must be directory"));
+					}
+
+					argument.model_directory = Some(model_directory);
+				} else {
+					return Err(Box::from("model directory must be provided"));
+				},
+				"--backend" => if let Some(raw_backend) = arguments.next() {
+					match raw_backend.to_ascii_lowercase().as_str() {
+						#[cfg(feature = "onnxruntime")]
+						"ort" | "onnxruntime" => argument.backend = Backend::OnnxRuntime,
+						#[cfg(not(feature = "onnxruntime"))]
+						"ort" | "onnxruntime" => return Err(Box::from("backend ort must be built with onnxruntime feature")),
+						"rust" => argument.backend = Backend::Rust,
+						_ => return Err(Box::from("backend must be one of ort, rust"))
+					}
+				} else {
+					return Err(Box::from("backend must be provided"));
+				},
+				"--fallback" => if let Some(raw_fallback) = arguments.next() {
+					argument.fallback = if raw_fallback.eq_ignore_ascii_case("none") {
+						None
+					} else {
+						match Model::try_from(raw_fallback.as_str()) {
+							Ok(Model::DeepQNetwork) | Err(_) => return Err(Box::from("fallback must be one of none, lru, lfu, arc, s3fifo, clock, gdsf, lecar")),
+							Ok(model) => Some(model)
+						}
+					};
+				} else {
+					return Err(Box::from("fallback must be provided"));
+				},
+				"--shadow" => if let Some(raw_shadow) = arguments.next() {
+					// capacity defaults to real cache capacity, which may be given later
+					let (raw_model, capacity): (&str, Option<usize>) = if let Some((raw_model, raw_capacity)) = raw_shadow.split_once(':') {
+						let capacity: usize = raw_capacity.parse::<usize>()?;
+
+						if capacity == 0 {
+							return Err(Box::from("shadow capacity must be greater than 0"));
+						}
+
+						(raw_model, Some(capacity))
+					} else {
+						(raw_shadow.as_str(), None)
+					};
+
+					argument.shadows.push((Model::try_from(raw_model)?, capacity));
+				} else {
+					return Err(Box::from("shadow must be provided"));
+				},
+				"--inference-budget" => if let Some(raw_inference_budget) = arguments.next() {
+					let inference_budget: u64 = raw_inference_budget.parse::<u64>()?;
+
+					argument.inference_budget = if inference_budget == 0 {
+						None
+					} else {
+						Some(inference_budget)
+					};
+				} else {
+					return Err(Box::from("inference budget must be provided"));
+				},
+				"--fallback-cooldown" => if let Some(raw_fallback_cooldown) = arguments.next() {
+					argument.fallback_cooldown = raw_fallback_cooldown.parse::<u64>()?;
+				} else {
+					return Err(Box::from("fallback cooldown must be provided"));
+				},
+				"--admission" | "-a" => if let Some(raw_admission) = arguments.next() {
+					match raw_admission.to_ascii_lowercase().as_str() {
+						"none" => argument.admission = None,
+						"wtinylfu" | "tinylfu" | "windowtinyleastfrequentlyused" => argument.admission = Some(Admission::WindowTinyLeastFrequentlyUsed),
+						"dqn" | "deepqnetwork" => argument.admission = Some(Admission::DeepQNetwork),
+						_ => return Err(Box::from("admission must be one of none, wtinylfu, dqn"))
+					}
+				} else {
+					return Err(Box::from("admission must be provided"));
+				},
+				"--eviction-samples" => if let Some(raw_eviction_samples) = arguments.next() {
+					argument.eviction_samples = raw_eviction_samples.parse::<usize>()?;
+				} else {
+					return Err(Box::from("eviction samples must be provided"));
+				},
+				"--eviction-pool" => if let Some(raw_eviction_pool) = arguments.next() {
+					argument.eviction_pool = raw_eviction_pool.parse::<usize>()?;
+				} else {
+					return Err(Box::from("eviction pool must be provided"));
+				},
+				"--time-unit" => if let Some(raw_time_unit) = arguments.next() {
+					match raw_time_unit.to_ascii_lowercase().as_str() {
+						"s" | "seconds" => argument.time_scale = 1,
+						"ms" | "milliseconds" => argument.time_scale = 1_000,
+						"us" | "microseconds" => argument.time_scale = 1_000_000,
+						_ => return Err(Box::from("time unit must be one of s, ms, us"))
+					}
+				} else {
+					return Err(Box::from("time unit must be provided"));
+				},
+				"--online-training" => argument.is_online_trained = true,
+				"--training-interval" => if let Some(raw_training_interval) = arguments.next() {
+					argument.training_interval = raw_training_interval.parse::<u64>()?;
+
+					if argument.training_interval == 0 {
+						return Err(Box::from("training interval must be greater than 0"));
+					}
+				} else {
+					return Err(Box::from("training interval must be provided"));
+				},
+				"--lfu-aging" => if let Some(raw_period) = arguments.next() {
+					let period: u64 = raw_period.parse::<u64>()?;
+
+					if period == 0 {
+						return Err(Box::from("lfu aging must be greater than 0"));
+					}
+
+					argument.lfu_aging = Some(Aging::Period(period));
+				} else {
+					return Err(Box::from("lfu aging must be provided"));
+				},
+				"--lfu-half-life" => if let Some(raw_half_life) = arguments.next() {
+					let half_life: u64 = raw_half_life.parse::<u64>()?;
+
+					if half_life == 0 {
+						return Err(Box::from("lfu half life must be greater than 0"));
+					}
+
+					argument.lfu_aging = Some(Aging::HalfLife(half_life));
+				} else {
+					return Err(Box::from("lfu half life must be provided"));
+				},
+				"--lecar-learning-rate" => if let Some(raw_learning_rate) = arguments.next() {
+					argument.lecar_learning_rate = raw_learning_rate.parse::<f64>()?;
+
+					if !(argument.lecar_learning_rate > 0.0) {
+						return Err(Box::from("lecar learning rate must be greater than 0"));
+					}
+				} else {
+					return Err(Box::from("lecar learning rate must be provided"));
+				},
+				"--lecar-discount" => if let Some(raw_discount) = arguments.next() {
+					let discount: f64 = raw_discount.parse::<f64>()?;
+
+					if !(discount > 0.0 && discount <= 1.0) {
+						return Err(Box::from("lecar discount must be greater than 0 and less than or equal to 1"));
+					}
+
+					argument.lecar_discount = Some(discount);
+				} else {
+					return Err(Box::from("lecar discount must be provided"));
+				},
 				"--capacity" | "-c" => if let Some(raw_capacity) = arguments.next() {
 					argument.capacity = raw_capacity.parse::<usize>()?;
 
@@ -92,6 +348,41 @@ impl Argument {
 				} else {
 					return Err(Box::from("capacity must be provided"));
 				},
+				"--max-memory" | "-M" => if let Some(raw_maximum_memory) = arguments.next() {
+					let maximum_memory: usize = parse_size(&raw_maximum_memory)?;
+
+					if maximum_memory == 0 {
+						return Err(Box::from("max memory must be greater than 0"));
+					}
+
+					argument.maximum_memory = Some(maximum_memory);
+				} else {
+					return Err(Box::from("max memory must be provided"));
+				},
+				"--low-watermark" => if let Some(raw_low_watermark) = arguments.next() {
+					argument.low_watermark = raw_low_watermark.parse::<f64>()?;
+
+					if !(argument.low_watermark > 0.0 && argument.low_watermark <= 1.0) {
+						return Err(Box::from("low watermark must be greater than 0 and less than or equal to 1"));
+					}
+				} else {
+					return Err(Box::from("low watermark must be provided"));
+				},
+				"--background-eviction" => argument.is_background_evicted = true,
+				"--replay" => if let Some(replay) = arguments.next() {
+					argument.replay = Some(replay);
+				} else {
+					return Err(Box::from("replay must be provided"));
+				},
+				"--eviction-interval" => if let Some(raw_eviction_interval) = arguments.next() {
+					argument.eviction_interval = raw_eviction_interval.parse::<u64>()?;
+
+					if argument.eviction_interval == 0 {
+						return Err(Box::from("eviction interval must be greater than 0"));
+					}
+				} else {
+					return Err(Box::from("eviction interval must be provided"));
+				},
 				"--directory" | "-d" => if let Some(directory) = arguments.next() {
 					argument.directory = directory;
 
@@ -101,6 +392,30 @@ impl Argument {
 				} else {
 					return Err(Box::from("directory must be provided"));
 				}
+				"--sweep-interval" | "-s" => if let Some(raw_sweep_interval) = arguments.next() {
+					argument.sweep_interval = raw_sweep_interval.parse::<u64>()?;
+
+					if argument.sweep_interval == 0 {
+						return Err(Box::from("sweep interval must be greater than 0"));
+					}
+				} else {
+					return Err(Box::from("sweep interval must be provided"));
+				},
+				"--experience" => argument.is_experience_logged = true,
+				"--experience-rows" => if let Some(raw_experience_rows) = arguments.next() {
+					argument.experience_rows = raw_experience_rows.parse::<usize>()?;
+
+					if argument.experience_rows == 0 {
+						return Err(Box::from("experience rows must be greater than 0"));
+					}
+				} else {
+					return Err(Box::from("experience rows must be provided"));
+				},
+				"--experience-files" => if let Some(raw_experience_files) = arguments.next() {
+					argument.experience_files = raw_experience_files.parse::<usize>()?;
+				} else {
+					return Err(Box::from("experience files must be provided"));
+				},
 				"--host" | "-H" => if let Some(raw_host) = arguments.next() {
 					argument.host = raw_host.parse::<Ipv4Addr>()?;
 				} else {
@@ -123,9 +438,38 @@ impl Argument {
 					print!("Usage: {} [OPTIONS]
 
 Options:
-  -m, --model <MODEL>          Set cache model [DQN, LRU, LFU] (default: DQN)
-  -c, --capacity <CAPACITY>    Set cache capacity (default: 128)
+  -m, --model <MODEL>          Set cache model [AUTO, DQN, LRU, LFU, ARC, S3FIFO, CLOCK, GDSF, LECAR] (default: DQN)
+      --auto-models <MODELS>   Set comma separated experts of AUTO model, first starts active (default: DQN,LRU,LFU)
+      --auto-window <LOOKUPS>  Compare AUTO experts every lookups (default: 10000)
+      --auto-margin <RATE>     Switch AUTO model only when expert leads by hit rate (default: 0.02)
+      --model-path <PATH>      Load DQN model from ONNX file (default: embedded)
+      --model-directory <DIRECTORY> Allow RELOAD of ONNX files under directory besides model path (default: none)
+      --backend <BACKEND>      Set DQN inference backend [ORT, RUST] (default: ORT if built with onnxruntime)
+      --fallback <MODEL>       Set model taking over failed or slow DQN [NONE, LRU, LFU, ARC, S3FIFO, CLOCK, GDSF, LECAR] (default: LRU)
+      --shadow <MODEL[:CAPACITY]> Track hit rate of MODEL on key only ghost cache, repeatable (default: none)
+      --inference-budget <US>  Fall back when DQN inference takes longer, 0 for unlimited (default: 0)
+      --fallback-cooldown <SECS> Retry DQN after seconds of fallback (default: 60)
+  -a, --admission <ADMISSION>  Set cache admission [NONE, WTINYLFU, DQN] (default: NONE)
+      --eviction-samples <K>   Score K random entries per DQN eviction, 0 for all (default: 0)
+      --eviction-pool <SIZE>   Carry SIZE best DQN candidates between samplings (default: 0)
+      --time-unit <UNIT>       Set DQN recency feature unit matching trained c_time [S, MS, US] (default: S)
+      --online-training        Fine-tune DQN on live traffic, requires RUST backend
+      --training-interval <SECS> Publish or revert fine-tuned DQN every seconds (default: 300)
+      --lfu-aging <OPERATIONS> Halve LFU access counts every operations (default: never)
+      --lfu-half-life <SECS>   Halve LFU access counts every seconds (default: never)
+      --lecar-learning-rate <RATE> Set LECAR learning rate (default: 0.45)
+      --lecar-discount <DISCOUNT> Set LECAR regret discount (default: 0.005^(1/CAPACITY))
+  -c, --capacity <CAPACITY>    Set cache capacity in entries, still enforced with -M (default: 128)
+  -M, --max-memory <BYTES>     Set cache memory budget [K, M, G], evicting at whichever of it and -c is hit first (default: unlimited)
+      --low-watermark <RATIO>  Evict in one batch down to ratio of capacity and memory once full (default: 1)
+      --background-eviction    Evict down to low watermark on background thread, inline only at capacity
+      --eviction-interval <MS> Set background eviction check interval (default: 10)
   -d, --directory <DIRECTORY>  Set data directory (default: ./data)
+  -s, --sweep-interval <SECS>  Set expired key sweep interval (default: 1)
+      --experience             Log DQN transitions to DIRECTORY/.experience as npy files
+      --experience-rows <ROWS> Rotate experience file after rows (default: 1048576)
+      --experience-files <COUNT> Keep newest experience files, 0 for all (default: 8)
+      --replay <TRACE>         Replay thesios csv trace offline, report DQN, LRU, LFU against Belady and exit
   -H, --host <HOST>            Set server host (default: 127.0.0.1)
   -p, --port <PORT>            Set server port (default: 5190)
   -v, --verbose                Enable verbose output
@@ -138,7 +482,7 @@ Options:
 				"--" => if let Some(_) = arguments.next() {
 					return Err(Box::from("positional arguments must not be provided"));
 				},
-				_ => return Err(Box::from(format!("Usage: {} [-m <MODEL>] [-c <CAPACITY>] [-d <DIRECTORY>] [-H <HOST>] [-p <PORT>] [-v] [-V] [-h]", executable)))
+				_ => return Err(Box::from(format!("Usage: {} [-m <MODEL>] [--auto-models <MODELS>] [--auto-window <LOOKUPS>] [--auto-margin <RATE>] [--model-path <PATH>] [--model-directory <DIRECTORY>] [--backend <BACKEND>] [--fallback <MODEL>] [--shadow <MODEL[:CAPACITY]>]... [--inference-budget <US>] [--fallback-cooldown <SECS>] [-a <ADMISSION>] [--eviction-samples <K>] [--eviction-pool <SIZE>] [--time-unit <UNIT>] [--online-training] [--training-interval <SECS>] [--lfu-aging <OPERATIONS>] [--lfu-half-life <SECS>] [--lecar-learning-rate <RATE>] [--lecar-discount <DISCOUNT>] [-c <CAPACITY>] [-M <BYTES>] [--low-watermark <RATIO>] [--background-eviction] [--eviction-interval <MS>] [-d <DIRECTORY>] [-s <SECS>] [--experience] [--experience-rows <ROWS>] [--experience-files <COUNT>] [--replay <TRACE>] [-H <HOST>] [-p <PORT>] [-v] [-V] [-h]", executable)))
 			}
 		}
 
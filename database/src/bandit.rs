@@ -0,0 +1,154 @@
+use std::{
+	fmt::Write,
+	sync::{Arc, Mutex}
+};
+use crate::{
+	cache::{Entry, Model},
+	common::Result,
+	network::Network,
+	shadow::Shadow,
+	error
+};
+
+// weight of latest window in arm score, older windows fade geometrically
+const DECAY: f64 = 0.5;
+
+struct Arm {
+	// ghost replaying this model at real capacity and memory budget, apart from --shadow ones
+	shadow: Shadow,
+	// shadow counts when current window started
+	hit_count: u64,
+	miss_count: u64,
+	score: Option<f64>
+}
+
+// every arm is observed through its ghost, so no pulls are wasted on exploration and only hysteresis keeps choice stable
+pub struct Bandit {
+	arms: Vec<Arm>,
+	active: usize,
+	window: u64,
+	lookup_count: u64,
+	margin: f64,
+	switch_count: u64
+}
+
+impl Bandit {
+	pub fn new(shadows: Vec<Shadow>, active: usize, window: u64, margin: f64) -> Self {
+		Bandit {
+			arms: shadows.into_iter()
+				.map(|shadow: Shadow| Arm {
+					shadow: shadow,
+					hit_count: 0,
+					miss_count: 0,
+					score: None
+				})
+				.collect::<Vec<Arm>>(),
+			active: active,
+			window: window,
+			lookup_count: 0,
+			margin: margin,
+			switch_count: 0
+		}
+	}
+
+	pub fn get(self: &mut Self, key: &str, now: u64, entry: Option<&Entry>) {
+		for arm in &mut self.arms {
+			if let Err(error) = arm.shadow.get(key, now, entry) {
+				error!("{} from auto expert {}\n", error, arm.shadow.model());
+			}
+		}
+	}
+
+	pub fn set(self: &mut Self, key: &str, entry: &Entry, now: u64) {
+		for arm in &mut self.arms {
+			if let Err(error) = arm.shadow.set(key, entry, now) {
+				error!("{} from auto expert {}\n", error, arm.shadow.model());
+			}
+		}
+	}
+
+	pub fn remove(self: &mut Self, key: &str) {
+		for arm in &mut self.arms {
+			arm.shadow.remove(key);
+		}
+	}
+
+	// returns how many dqn arms took network
+	pub fn reload(self: &mut Self, network: &Arc<Mutex<Box<dyn Network + Send>>>) -> Result<usize> {
+		let mut reload_count: usize = 0;
+
+		for arm in &mut self.arms {
+			if arm.shadow.model() == Model::DeepQNetwork {
+				arm.shadow.reload(network.clone())?;
+				reload_count += 1;
+			}
+		}
+
+		Ok(reload_count)
+	}
+
+	// returns model to switch to when window closes with another arm clearly ahead
+	pub fn observe(self: &mut Self) -> Option<Model> {
+		self.lookup_count += 1;
+
+		if self.lookup_count < self.window {
+			return None;
+		}
+
+		self.lookup_count = 0;
+
+		for arm in &mut self.arms {
+			let (hit_count, miss_count): (u64, u64) = arm.shadow.counts();
+			let hits: u64 = hit_count - arm.hit_count;
+			let lookups: u64 = hits + miss_count - arm.miss_count;
+			let hit_rate: f64 = if lookups == 0 {
+				0.0
+			} else {
+				hits as f64 / lookups as f64
+			};
+
+			arm.hit_count = hit_count;
+			arm.miss_count = miss_count;
+			arm.score = Some(if let Some(score) = arm.score {
+				(1.0 - DECAY) * score + DECAY * hit_rate
+			} else {
+				hit_rate
+			});
+		}
+
+		let mut best: usize = self.active;
+
+		for (i, arm) in self.arms.iter().enumerate() {
+			if arm.score > self.arms[best].score {
+				best = i;
+			}
+		}
+
+		if best != self.active && self.arms[best].score.unwrap_or(0.0) > self.arms[self.active].score.unwrap_or(0.0) + self.margin {
+			self.active = best;
+			self.switch_count += 1;
+
+			Some(self.arms[best].shadow.model())
+		} else {
+			None
+		}
+	}
+
+	pub fn score(self: &Self, model: Model) -> f64 {
+		self.arms.iter()
+			.find(|arm: &&Arm| arm.shadow.model() == model)
+			.and_then(|arm: &Arm| arm.score)
+			.unwrap_or(0.0)
+	}
+
+	pub fn report(self: &Self, report: &mut String) {
+		let _ = write!(report, "auto_switches:{}\n", self.switch_count);
+
+		for arm in &self.arms {
+			let (hit_count, miss_count): (u64, u64) = arm.shadow.counts();
+			let model: Model = arm.shadow.model();
+
+			let _ = write!(report, "auto_{}_hits:{}\nauto_{}_misses:{}\nauto_{}_score:{:.4}\n", model, hit_count, model, miss_count, model, arm.score.unwrap_or(0.0));
+		}
+	}
+}
\ No newline at end of file
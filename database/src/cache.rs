@@ -1,101 +1,749 @@
 use std::{
-	collections::HashMap,
-	fmt::{Debug, Formatter, Result as _Result}
+	error::Error,
+	fmt::{Debug, Display, Formatter, Result as _Result, Write},
+	mem::{replace, size_of},
+	sync::{
+		atomic::{AtomicBool, Ordering},
+		mpsc::{SyncSender, TrySendError},
+		Arc,
+		Mutex,
+		MutexGuard,
+		PoisonError
+	},
+	time::Duration
 };
 use crate::{
-	common::{ARGUMENT, Result, unix_epoch},
-	model::{DeepQNetwork, LeastFrequentlyUsed, LeastRecentlyUsed},
+	admission::WindowTinyLeastFrequentlyUsed,
+	bandit::Bandit,
+	common::{ARGUMENT, AccessClock, Result, access_time, unix_epoch},
+	entries::Entries,
+	feature::{FEATURE_COUNT, FeatureExtractor},
+	model::{
+		AdaptiveReplacementCache,
+		Clock,
+		DeepQNetwork,
+		Fallback,
+		LeastFrequentlyUsed,
+		GreedyDualSizeFrequency,
+		LearningCacheReplacement,
+		LeastRecentlyUsed,
+		S3FirstInFirstOut
+	},
+	network::Network,
+	perceptron::Perceptron,
+	shadow::Shadow,
+	trainer::Transition,
 	debug,
-	info
+	error,
+	info,
+	warn
 };
 
+// key and entry headers kept per cached entry besides key and value bytes
+const ENTRY_OVERHEAD: usize = size_of::<String>() + size_of::<Entry>();
+
 pub struct Entry {
 	pub value: String,
+	// kept apart from value so ghost entries can drop value but still be sized
+	pub length: usize,
 	pub accessed_at: u64,
-	pub access_count: u64
+	pub access_count: u64,
+	pub expires_at: Option<u64>,
+	// microseconds spent reading value from storage, 0 if never fetched
+	pub cost: u64
 }
 
 impl Debug for Entry {
 	fn fmt(self: &Self, formatter: &mut Formatter<'_>) -> _Result {
 		formatter.debug_struct("")
-			.field("size", &self.value.len())
+			.field("size", &self.length)
 			.field("accessed_at", &self.accessed_at)
 			.field("access_count", &self.access_count)
+			.field("expires_at", &self.expires_at)
+			.field("cost", &self.cost)
 			.finish()
 	}
 }
 
 impl Entry {
-	pub fn new(value: &str) -> Result<Entry> {
+	pub fn new(value: &str, expires_at: Option<u64>) -> Result<Entry> {
 		Ok(Entry {
 			value: value.to_owned(),
-			accessed_at: unix_epoch()?,
-			access_count: 1
+			length: value.len(),
+			accessed_at: access_time()?,
+			access_count: 1,
+			expires_at: expires_at,
+			cost: 0
 		})
 	}
+
+	pub fn is_expired(self: &Self, now: u64) -> bool {
+		if let Some(expires_at) = self.expires_at {
+			expires_at <= now
+		} else {
+			false
+		}
+	}
+
+	pub fn size(self: &Self, key: &str) -> usize {
+		key.len() + self.length + ENTRY_OVERHEAD
+	}
+
+	// value dropped copy tracked by shadow caches
+	pub fn to_ghost(self: &Self) -> Entry {
+		Entry {
+			value: String::new(),
+			length: self.length,
+			accessed_at: self.accessed_at,
+			access_count: 1,
+			expires_at: None,
+			cost: self.cost
+		}
+	}
 }
 
+// candidates of background eviction with network to score them, so inference runs outside cache lock
+pub struct Candidates {
+	pub keys: Vec<String>,
+	pub inputs: Vec<f32>,
+	pub network: Arc<Mutex<Box<dyn Network + Send>>>
+}
+
+impl Candidates {
+	pub fn score(self: Self) -> Result<(Vec<String>, Vec<f32>)> {
+		let length: usize = self.keys.len();
+		let scores: Vec<f32> = self.network.lock()
+			.map_err(|error: PoisonError<MutexGuard<'_, Box<dyn Network + Send>>>| error.to_string())?
+			.run(self.inputs, length)?;
+
+		Ok((self.keys, scores))
+	}
+}
+
+// hooks are called after entries changed, so policies can keep their own state instead of scanning entries
 pub trait Evictor {
-	fn select_victim(self: &mut Self, entries: &HashMap<String, Entry>) -> Result<String>;
+	fn select_victim(self: &mut Self, entries: &Entries) -> Result<String>;
+
+	// may return fewer than count, cache asks again while still above low watermark
+	fn select_victims(self: &mut Self, entries: &Entries, _count: usize) -> Result<Vec<String>> {
+		Ok(vec![self.select_victim(entries)?])
+	}
+
+	// background evictor scores these outside cache lock, none when policy selects cheaply under lock
+	fn sample_candidates(self: &mut Self, _entries: &Entries) -> Result<Option<Candidates>> {
+		Ok(None)
+	}
+
+	// lowest count of sampled candidates once scored, cache checks they are still cached
+	fn rank_candidates(self: &mut Self, keys: Vec<String>, scores: &[f32], count: usize) -> Vec<String> {
+		let mut indices: Vec<usize> = (0..keys.len()).collect::<Vec<usize>>();
+
+		indices.sort_by(|a: &usize, b: &usize| scores[*a].total_cmp(&scores[*b]));
+
+		indices.iter()
+			.take(count.max(1))
+			.map(|index: &usize| keys[*index].clone())
+			.collect::<Vec<String>>()
+	}
+
+	fn on_insert(self: &mut Self, _key: &str, _entry: &Entry) {}
+
+	fn on_access(self: &mut Self, _key: &str, _entry: &Entry) {}
+
+	fn on_remove(self: &mut Self, _key: &str, _entry: &Entry, _is_evicted: bool) {}
+
+	// ranks missed candidate against chosen victim by policy's own scoring, admitting when policy cannot tell
+	fn admit(self: &mut Self, _candidate: &Entry, _victim: &Entry) -> Result<bool> {
+		Ok(true)
+	}
+
+	// swaps in network already loaded and checked outside cache lock, keeping current one on error
+	fn reload(self: &mut Self, _network: Arc<Mutex<Box<dyn Network + Send>>>) -> Result<()> {
+		Err(Box::from("model must be reloadable"))
+	}
+
+	// swaps in network trained online, keeping current one on error
+	fn replace_network(self: &mut Self, _network: Perceptron) -> Result<()> {
+		Err(Box::from("model must be trainable"))
+	}
+
+	// appends name:value lines served by STATS
+	fn report(self: &Self, _report: &mut String) {}
+}
+
+// decides whether missed key may displace victim, which stops one-off keys from flooding cache
+pub trait Admitter {
+	fn record(self: &mut Self, key: &str);
+
+	// key to evict for missed candidate, victim or one admitter prefers to drop, none to refuse candidate
+	fn select_evictee(self: &mut Self, candidate_key: &str, victim_key: &str) -> Option<String>;
+
+	fn on_insert(self: &mut Self, _key: &str) {}
+
+	fn on_remove(self: &mut Self, _key: &str) {}
 }
 
 #[derive(Debug, Clone, Copy)]
+pub enum Admission {
+	WindowTinyLeastFrequentlyUsed,
+	DeepQNetwork
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Model {
 	DeepQNetwork,
 	LeastRecentlyUsed,
-	LeastFrequentlyUsed
+	LeastFrequentlyUsed,
+	AdaptiveReplacementCache,
+	S3FirstInFirstOut,
+	Clock,
+	GreedyDualSizeFrequency,
+	LearningCacheReplacement,
+	// offline only, needs whole trace ahead so it is never parsed from arguments
+	Belady
+}
+
+impl TryFrom<&str> for Model {
+	type Error = Box<dyn Error>;
+
+	fn try_from(value: &str) -> Result<Self> {
+		Ok(match value.to_ascii_lowercase().as_str() {
+			"dqn" | "deepqnetwork" => Model::DeepQNetwork,
+			"lru" | "leastrecentlyused" => Model::LeastRecentlyUsed,
+			"lfu" | "leastfrequentlyused" => Model::LeastFrequentlyUsed,
+			"arc" | "adaptivereplacementcache" => Model::AdaptiveReplacementCache,
+			"s3fifo" | "s3firstinfirstout" => Model::S3FirstInFirstOut,
+			"clock" => Model::Clock,
+			"gdsf" | "greedydualsizefrequency" => Model::GreedyDualSizeFrequency,
+			"lecar" | "learningcachereplacement" => Model::LearningCacheReplacement,
+			_ => return Err(Box::from("model must be one of dqn, lru, lfu, arc, s3fifo, clock, gdsf, lecar"))
+		})
+	}
+}
+
+impl Display for Model {
+	fn fmt(self: &Self, formatter: &mut Formatter<'_>) -> _Result {
+		formatter.write_str(match self {
+			Model::DeepQNetwork => "dqn",
+			Model::LeastRecentlyUsed => "lru",
+			Model::LeastFrequentlyUsed => "lfu",
+			Model::AdaptiveReplacementCache => "arc",
+			Model::S3FirstInFirstOut => "s3fifo",
+			Model::Clock => "clock",
+			Model::GreedyDualSizeFrequency => "gdsf",
+			Model::LearningCacheReplacement => "lecar",
+			Model::Belady => "belady"
+		})
+	}
+}
+
+// halves access counts every period of operations or seconds
+#[derive(Debug, Clone, Copy)]
+pub enum Aging {
+	Period(u64),
+	HalfLife(u64)
 }
 
 pub struct Cache {
-	entries: HashMap<String, Entry>,
+	entries: Entries,
 	model: Box<dyn Evictor + Send>,
-	capacity: usize
+	active_model: Model,
+	// experts waiting for bandit, holding no entries
+	idle_models: Vec<(Model, Box<dyn Evictor + Send>)>,
+	bandit: Option<Bandit>,
+	features: FeatureExtractor,
+	// writer thread does disk io, so logging never holds cache lock
+	experience: Option<SyncSender<Transition>>,
+	experience_drop_count: u64,
+	trainer: Option<SyncSender<Transition>>,
+	// reloaded weights waiting for trainer, so its next publish does not overwrite them
+	reloaded_network: Option<Perceptron>,
+	shadows: Vec<Shadow>,
+	admitter: Option<Box<dyn Admitter + Send>>,
+	is_learned_admission: bool,
+	capacity: usize,
+	size: usize,
+	maximum_size: Option<usize>,
+	// full cache evicts until below these, so selection is paid once per batch instead of per miss
+	low_capacity: usize,
+	low_maximum_size: Option<usize>,
+	is_background_evicted: bool,
+	// raised once inserts pass low watermark, so idle background evictor never takes lock
+	is_eviction_due: Arc<AtomicBool>,
+	aging: Option<Aging>,
+	operation_count: u64,
+	aged_at: u64,
+	hit_count: u64,
+	miss_count: u64,
+	eviction_count: u64,
+	forced_eviction_count: u64,
+	rejection_count: u64
 }
 
 impl Cache {
-	pub fn new(model: Model, capacity: usize) -> Result<Cache> {
-		info!("cache using {:?} initialized with capacity of {}\n", model, capacity);
+	pub fn new(model: Model, capacity: usize, maximum_size: Option<usize>) -> Result<Cache> {
+		// automatic mode starts from first expert until bandit picks another
+		let model: Model = if ARGUMENT.is_automatic {
+			ARGUMENT.automatic_models[0]
+		} else {
+			model
+		};
+
+		if let Some(maximum_size) = maximum_size {
+			info!("cache using {:?} initialized with capacity of {} and {} bytes\n", model, capacity, maximum_size);
+		} else {
+			info!("cache using {:?} initialized with capacity of {}\n", model, capacity);
+		}
+
+		let aging: Option<Aging> = if model == Model::LeastFrequentlyUsed || ARGUMENT.is_automatic {
+			ARGUMENT.lfu_aging
+		} else {
+			None
+		};
+
+		let low_capacity: usize = ((capacity as f64 * ARGUMENT.low_watermark) as usize).max(1);
+		let low_maximum_size: Option<usize> = maximum_size.map(|maximum_size: usize| (maximum_size as f64 * ARGUMENT.low_watermark) as usize);
+
+		if ARGUMENT.is_background_evicted {
+			if ARGUMENT.low_watermark >= 1.0 {
+				return Err(Box::from("background eviction must use low watermark less than 1"));
+			}
+
+			info!("cache evicting in background down to {} of capacity\n", ARGUMENT.low_watermark);
+		} else if ARGUMENT.low_watermark < 1.0 {
+			info!("cache evicting in batches down to {} of capacity\n", ARGUMENT.low_watermark);
+		}
+
+		if let Some(aging) = aging {
+			info!("cache aging access counts with {:?}\n", aging);
+		}
+
+		if let Some(admission) = ARGUMENT.admission {
+			if matches!(admission, Admission::DeepQNetwork) && model != Model::DeepQNetwork {
+				return Err(Box::from("dqn admission must use dqn model"));
+			}
+
+			info!("cache admitting with {:?}\n", admission);
+		}
+
+		let shadows: Vec<Shadow> = ARGUMENT.shadows.iter()
+			.map(|(model, shadow_capacity): &(Model, Option<usize>)| {
+				let shadow_capacity: usize = shadow_capacity.unwrap_or(capacity);
+				// byte budget shrinks or grows with capacity, so variants differ only in size
+				let shadow_maximum_size: Option<usize> = maximum_size.map(|maximum_size: usize| (maximum_size as f64 * shadow_capacity as f64 / capacity as f64) as usize);
+
+				info!("cache shadowed by {} with capacity of {}\n", model, shadow_capacity);
+
+				Shadow::new(*model, shadow_capacity, shadow_maximum_size)
+			})
+			.collect::<Result<Vec<Shadow>>>()?;
+		let bandit: Option<Bandit> = if ARGUMENT.is_automatic {
+			let experts: Vec<Shadow> = ARGUMENT.automatic_models.iter()
+				.map(|expert: &Model| Shadow::new(*expert, capacity, maximum_size))
+				.collect::<Result<Vec<Shadow>>>()?;
+
+			info!("cache switching automatically every {} lookups with margin of {}\n", ARGUMENT.automatic_window, ARGUMENT.automatic_margin);
+
+			Some(Bandit::new(experts, 0, ARGUMENT.automatic_window, ARGUMENT.automatic_margin))
+		} else {
+			None
+		};
+		// experts are built once, so switching loads no model on lookup path and keeps reloaded network
+		let idle_models: Vec<(Model, Box<dyn Evictor + Send>)> = if ARGUMENT.is_automatic {
+			ARGUMENT.automatic_models[1..].iter()
+				.map(|expert: &Model| -> Result<(Model, Box<dyn Evictor + Send>)> {
+					Ok((*expert, Cache::create_primary_model(*expert, capacity)?))
+				})
+				.collect::<Result<Vec<(Model, Box<dyn Evictor + Send>)>>>()?
+		} else {
+			Vec::new()
+		};
 
 		Ok(Cache {
-			entries: HashMap::with_capacity(capacity),
-			model: match model {
-				Model::DeepQNetwork => Box::new(DeepQNetwork::new()?),
-				Model::LeastFrequentlyUsed => Box::new(LeastFrequentlyUsed {}),
-				Model::LeastRecentlyUsed => Box::new(LeastRecentlyUsed {})
-			},
-			capacity: capacity
+			entries: Entries::with_capacity(capacity),
+			model: Cache::create_primary_model(model, capacity)?,
+			active_model: model,
+			idle_models: idle_models,
+			bandit: bandit,
+			features: FeatureExtractor::new(capacity),
+			experience: None,
+			experience_drop_count: 0,
+			trainer: None,
+			reloaded_network: None,
+			shadows: shadows,
+			admitter: ARGUMENT.admission.and_then(|admission: Admission| -> Option<Box<dyn Admitter + Send>> {
+				match admission {
+					Admission::WindowTinyLeastFrequentlyUsed => Some(Box::new(WindowTinyLeastFrequentlyUsed::new(capacity))),
+					// network lives in model, so model scores admission itself
+					Admission::DeepQNetwork => None
+				}
+			}),
+			is_learned_admission: matches!(ARGUMENT.admission, Some(Admission::DeepQNetwork)),
+			capacity: capacity,
+			size: 0,
+			maximum_size: maximum_size,
+			low_capacity: low_capacity,
+			low_maximum_size: low_maximum_size,
+			is_background_evicted: ARGUMENT.is_background_evicted,
+			is_eviction_due: Arc::new(AtomicBool::new(false)),
+			aging: aging,
+			operation_count: 0,
+			aged_at: unix_epoch()?,
+			hit_count: 0,
+			miss_count: 0,
+			eviction_count: 0,
+			forced_eviction_count: 0,
+			rejection_count: 0
 		})
 	}
 
+	// dqn serves behind fallback when one is configured
+	fn create_primary_model(model: Model, capacity: usize) -> Result<Box<dyn Evictor + Send>> {
+		Ok(if let (Model::DeepQNetwork, Some(fallback)) = (model, ARGUMENT.fallback) {
+			info!("cache falling back to {} for {} seconds on model failure\n", fallback, ARGUMENT.fallback_cooldown);
+
+			Box::new(Fallback::new(Cache::create_model(model, capacity)?, Cache::create_model(fallback, capacity)?, ARGUMENT.inference_budget.map(Duration::from_micros), ARGUMENT.fallback_cooldown))
+		} else {
+			Cache::create_model(model, capacity)?
+		})
+	}
+
+	pub fn create_model(model: Model, capacity: usize) -> Result<Box<dyn Evictor + Send>> {
+		Ok(match model {
+			Model::DeepQNetwork => Box::new(DeepQNetwork::new(ARGUMENT.model_path.as_deref(), ARGUMENT.backend, capacity, ARGUMENT.eviction_samples, ARGUMENT.eviction_pool, AccessClock::System)?),
+			Model::LeastFrequentlyUsed => Box::new(LeastFrequentlyUsed {}),
+			Model::LeastRecentlyUsed => Box::new(LeastRecentlyUsed {}),
+			Model::AdaptiveReplacementCache => Box::new(AdaptiveReplacementCache::new(capacity)),
+			Model::S3FirstInFirstOut => Box::new(S3FirstInFirstOut::new(capacity)),
+			Model::Clock => Box::new(Clock::new(capacity)),
+			Model::GreedyDualSizeFrequency => Box::new(GreedyDualSizeFrequency::new()),
+			Model::LearningCacheReplacement => Box::new(LearningCacheReplacement::new(capacity, ARGUMENT.lecar_learning_rate, ARGUMENT.lecar_discount)),
+			Model::Belady => return Err(Box::from("belady model must be created from replayed trace"))
+		})
+	}
+
+	fn age(self: &mut Self, now: u64) {
+		let shift: u64 = match self.aging {
+			Some(Aging::Period(period)) => {
+				self.operation_count += 1;
+
+				if self.operation_count < period {
+					return;
+				}
+
+				self.operation_count = 0;
+
+				1
+			},
+			Some(Aging::HalfLife(half_life)) => {
+				let elapsed: u64 = now.saturating_sub(self.aged_at);
+
+				if elapsed < half_life {
+					return;
+				}
+
+				self.aged_at += elapsed / half_life * half_life;
+
+				elapsed / half_life
+			},
+			None => return
+		};
+
+		self.entries.age(shift.min(u32::MAX as u64) as u32);
+
+		if ARGUMENT.is_verbose {
+			debug!("aged access counts by {} halvings\n", shift);
+		}
+	}
+
+	fn is_full(self: &Self, size: usize) -> bool {
+		if let Some(maximum_size) = self.maximum_size {
+			self.size + size > maximum_size
+		} else {
+			false
+		}
+	}
+
+	fn is_above_low_watermark(self: &Self) -> bool {
+		self.entries.len() >= self.low_capacity || if let Some(low_maximum_size) = self.low_maximum_size {
+			self.size > low_maximum_size
+		} else {
+			false
+		}
+	}
+
+	// victims needed to get below low watermark and fit entry of size in budget, bytes estimated from average entry size
+	fn count_victims(self: &Self, size: usize) -> usize {
+		let mut count: usize = (self.entries.len() + 1).saturating_sub(self.low_capacity);
+
+		if let (Some(low_maximum_size), Some(maximum_size)) = (self.low_maximum_size, self.maximum_size) {
+			let excess: usize = self.size.saturating_sub(low_maximum_size)
+				.max((self.size + size).saturating_sub(maximum_size));
+
+			if excess != 0 {
+				count = count.max(excess.div_ceil((self.size / self.entries.len().max(1)).max(1)));
+			}
+		}
+
+		count.clamp(1, self.entries.len().max(1))
+	}
+
+	pub fn eviction_due(self: &Self) -> Arc<AtomicBool> {
+		self.is_eviction_due.clone()
+	}
+
+	// first half of background batch, none once below low watermark or when policy already evicted under lock
+	pub fn sample_eviction(self: &mut Self) -> Result<Option<Candidates>> {
+		if self.entries.len() == 0 || !self.is_above_low_watermark() {
+			// cleared under lock, so insert raising it again cannot be missed
+			self.is_eviction_due.store(false, Ordering::Relaxed);
+
+			return Ok(None);
+		}
+
+		if let Some(candidates) = self.model.sample_candidates(&self.entries)? {
+			return Ok(Some(candidates));
+		}
+
+		self.evict_batch()?;
+
+		Ok(None)
+	}
+
+	// second half of background batch, requests ran while scoring so victims and watermark are checked again
+	pub fn finish_eviction(self: &mut Self, keys: Vec<String>, scores: &[f32]) -> Result<()> {
+		if self.entries.len() == 0 || !self.is_above_low_watermark() {
+			return Ok(());
+		}
+
+		let victim_keys: Vec<String> = self.model.rank_candidates(keys, scores, self.count_victims(0));
+		let mut eviction_count: usize = 0;
+
+		for victim_key in &victim_keys {
+			if !self.is_above_low_watermark() {
+				break;
+			}
+
+			if self.entries.get(victim_key).is_some() {
+				self.evict_key(victim_key)?;
+
+				eviction_count += 1;
+			}
+		}
+
+		if ARGUMENT.is_verbose {
+			debug!("evicted {} keys in background\n", eviction_count);
+		}
+
+		Ok(())
+	}
+
+	// evicts single batch toward low watermark, so worker releases lock between batches and requests interleave
+	pub fn evict_batch(self: &mut Self) -> Result<bool> {
+		if self.entries.len() == 0 || !self.is_above_low_watermark() {
+			return Ok(false);
+		}
+
+		let victim_keys: Vec<String> = self.model.select_victims(&self.entries, self.count_victims(0))?;
+
+		for victim_key in &victim_keys {
+			self.evict_key(victim_key)?;
+		}
+
+		if ARGUMENT.is_verbose {
+			debug!("evicted {} keys in background\n", victim_keys.len());
+		}
+
+		Ok(true)
+	}
+
+	fn evict(self: &mut Self) -> Result<()> {
+		let victim_key: String = self.model.select_victim(&self.entries)?;
+
+		self.evict_key(&victim_key)
+	}
+
+	fn evict_key(self: &mut Self, victim_key: &str) -> Result<()> {
+		if let Some(old_entry) = self.entries.remove(victim_key) {
+			self.size -= old_entry.size(victim_key);
+			self.eviction_count += 1;
+
+			if self.is_observed() {
+				let state: [f32; FEATURE_COUNT] = self.features.extract(access_time()?, &old_entry);
+
+				self.observe(state, 0.0, None);
+			}
+
+			self.model.on_remove(victim_key, &old_entry, true);
+
+			if let Some(admitter) = &mut self.admitter {
+				admitter.on_remove(victim_key);
+			}
+
+			if ARGUMENT.is_verbose {
+				debug!("evicted {:?}:{:#?}\n", victim_key, old_entry);
+			}
+		} else {
+			// policies keeping own state must never hand back unknown key, or eviction would loop forever
+			return Err(Box::from(format!("victim {:?} must be cached", victim_key)));
+		}
+
+		Ok(())
+	}
+
+	// client writes are always cached, admission only filters values fetched from storage on miss
 	pub fn set(self: &mut Self, key: &str, entry: Entry) -> Result<()> {
+		self.insert(key, entry, false)
+	}
+
+	pub fn fill(self: &mut Self, key: &str, entry: Entry) -> Result<()> {
+		self.insert(key, entry, true)
+	}
+
+	fn insert(self: &mut Self, key: &str, entry: Entry, is_filled: bool) -> Result<()> {
 		let entries: String = if ARGUMENT.is_verbose {
 			format!("{:#?}", self.entries)
 		} else {
 			String::new()
 		};
+		let size: usize = entry.size(key);
+
+		self.age(unix_epoch()?);
+
+		if let Some(maximum_size) = self.maximum_size {
+			if size > maximum_size {
+				// value that can never fit must not leave stale value behind
+				self.remove(key);
+
+				if ARGUMENT.is_verbose {
+					debug!("skipped {:?}:{:#?} exceeding {} bytes\n", key, entry, maximum_size);
+				}
+
+				return Ok(());
+			}
+		}
+
+		for shadow in &mut self.shadows {
+			if let Err(error) = shadow.set(key, &entry, entry.accessed_at) {
+				error!("{} from shadow {}\n", error, shadow.name());
+			}
+		}
+
+		if let Some(bandit) = &mut self.bandit {
+			bandit.set(key, &entry, entry.accessed_at);
+		}
 
 		if let Some(old_entry) = self.entries.get_mut(key) {
+			self.size = self.size - old_entry.length + entry.length;
+
 			old_entry.value = entry.value;
-			old_entry.accessed_at = entry.accessed_at;
-			old_entry.access_count += entry.access_count;
+			old_entry.length = entry.length;
+			old_entry.expires_at = entry.expires_at;
 
-			if ARGUMENT.is_verbose {
-				debug!("set {:?}:{:#?} to {}\n", key, old_entry, entries);
+			if entry.cost != 0 {
+				old_entry.cost = entry.cost;
+			}
+
+			self.entries.touch(key, entry.accessed_at);
+
+			if let Some(entry) = self.entries.get(key) {
+				self.model.on_access(key, entry);
+
+				if ARGUMENT.is_verbose {
+					debug!("set {:?}:{:#?} to {}\n", key, entry, entries);
+				}
+			}
+
+			while self.is_full(0) {
+				self.evict()?;
 			}
 		} else {
-			if self.entries.len() == self.capacity {
-				let victim_key: String = self.model.select_victim(&self.entries)?;
+			if self.entries.len() >= self.capacity || self.is_full(size) {
+				let mut is_admission_checked: bool = !is_filled;
 
-				if let Some(old_entry) = self.entries.remove(&victim_key) {
-					if ARGUMENT.is_verbose {
-						debug!("evicted {:?}:{:#?} and set {:?}:{:#?} to {}\n", victim_key, old_entry, key, entry, entries);
+				// background evictor keeps below low watermark, so reaching capacity here only frees room for this entry
+				// incoming bytes count against memory budget only, so entry above low watermark cannot flush whole cache
+				while self.entries.len() != 0 && if self.is_background_evicted {
+					self.entries.len() >= self.capacity || self.is_full(size)
+				} else {
+					self.is_above_low_watermark() || self.is_full(size)
+				} {
+					let count: usize = if self.is_background_evicted {
+						1
+					} else {
+						self.count_victims(size)
+					};
+					let mut victim_keys: Vec<String> = self.model.select_victims(&self.entries, count)?;
+
+					// lowest ranked victim stands for whole batch, so rejection keeps every entry
+					if !is_admission_checked {
+						is_admission_checked = true;
+
+						let victim_key: String = victim_keys.first()
+							.ok_or("victims must not be empty")?
+							.clone();
+						let evictee: Option<String> = if let Some(admitter) = &mut self.admitter {
+							admitter.select_evictee(key, &victim_key)
+						} else if let (true, Some(victim)) = (self.is_learned_admission, self.entries.get(&victim_key)) {
+							// failed scoring must not stop caching, so candidate is admitted as without admission
+							let is_admitted: bool = self.model.admit(&entry, victim)
+								.unwrap_or_else(|error: Box<dyn Error>| {
+									error!("{} from admission\n", error);
+
+									true
+								});
+
+							is_admitted.then_some(victim_key.clone())
+						} else {
+							Some(victim_key.clone())
+						};
+
+						match evictee {
+							Some(evictee) => if evictee != victim_key {
+								// admitter dropped its own key instead, which takes lowest victim's place in batch
+								victim_keys.retain(|key: &String| *key != evictee);
+								victim_keys[0] = evictee;
+							},
+							None => {
+								self.rejection_count += 1;
+
+								if ARGUMENT.is_verbose {
+									debug!("rejected {:?}:{:#?} against {:?}\n", key, entry, victim_key);
+								}
+
+								return Ok(());
+							}
+						}
+					}
+
+					for victim_key in &victim_keys {
+						self.evict_key(victim_key)?;
+					}
+
+					if self.is_background_evicted {
+						self.forced_eviction_count += victim_keys.len() as u64;
 					}
 				}
-			} else if ARGUMENT.is_verbose {
+			}
+
+			if ARGUMENT.is_verbose {
 				debug!("set {:?}:{:#?} to {}\n", key, entry, entries);
 			}
 
+			self.size += size;
 			self.entries.insert(key.to_owned(), entry);
+
+			if let Some(entry) = self.entries.get(key) {
+				self.model.on_insert(key, entry);
+			}
+
+			if let Some(admitter) = &mut self.admitter {
+				admitter.on_insert(key);
+			}
+		}
+
+		if self.is_background_evicted && self.is_above_low_watermark() {
+			self.is_eviction_due.store(true, Ordering::Relaxed);
 		}
 
 		Ok(())
@@ -107,23 +755,256 @@ impl Cache {
 		} else {
 			String::new()
 		};
+		// expiry and aging count seconds, recency counts access time
+		let now: u64 = unix_epoch()?;
+		let accessed_at: u64 = access_time()?;
 
-		Ok(if let Some(entry) = self.entries.get_mut(key) {
-			entry.access_count += 1;
-			entry.accessed_at = unix_epoch()?;
+		self.age(now);
+
+		if let Some(admitter) = &mut self.admitter {
+			admitter.record(key);
+		}
+
+		for shadow in &mut self.shadows {
+			let entry: Option<&Entry> = self.entries.get(key)
+				.filter(|entry: &&Entry| !entry.is_expired(now));
+
+			if let Err(error) = shadow.get(key, accessed_at, entry) {
+				error!("{} from shadow {}\n", error, shadow.name());
+			}
+		}
+
+		if let Some(bandit) = &mut self.bandit {
+			bandit.get(key, accessed_at, self.entries.get(key)
+				.filter(|entry: &&Entry| !entry.is_expired(now)));
+
+			if let Some(model) = bandit.observe() {
+				self.switch_model(model)?;
+			}
+		}
+
+		Ok(if let Some(entry) = self.entries.get(key) {
+			if entry.is_expired(now) {
+				self.remove(key);
+				self.miss_count += 1;
+
+				return Ok(None);
+			}
+
+			self.hit_count += 1;
 
 			if ARGUMENT.is_verbose {
 				debug!("get {:?} from {}\n", key, entries);
 			}
 
-			Some(entry)
+			let state: Option<[f32; FEATURE_COUNT]> = if self.is_observed() {
+				Some(self.features.extract(accessed_at, entry))
+			} else {
+				None
+			};
+
+			self.entries.touch(key, accessed_at);
+
+			if let Some(entry) = self.entries.get(key) {
+				self.model.on_access(key, entry);
+
+				if let Some(state) = state {
+					let next_state: [f32; FEATURE_COUNT] = self.features.extract(accessed_at, entry);
+
+					self.observe(state, 1.0, Some(next_state));
+				}
+			}
+
+			self.entries.get(key)
 		} else {
+			self.miss_count += 1;
+
 			None
 		})
 	}
 
+	pub fn expire(self: &mut Self, key: &str, expires_at: Option<u64>) {
+		if let Some(entry) = self.entries.get_mut(key) {
+			entry.expires_at = expires_at;
+
+			if ARGUMENT.is_verbose {
+				debug!("expire {:?}:{:#?}\n", key, entry);
+			}
+		}
+	}
+
+	pub fn reload(self: &mut Self, network: Box<dyn Network + Send>, path: Option<&str>) -> Result<()> {
+		let reloaded_network: Option<Perceptron> = if self.trainer.is_some() {
+			network.to_perceptron()
+		} else {
+			None
+		};
+		// every dqn evictor shares one network, so idle expert, bandit and shadow ghosts score as served one does
+		let network: Arc<Mutex<Box<dyn Network + Send>>> = Arc::new(Mutex::new(network));
+		let mut reload_count: usize = 0;
+
+		if self.active_model == Model::DeepQNetwork {
+			self.model.reload(network.clone())?;
+			reload_count += 1;
+		}
+
+		for (model, evictor) in &mut self.idle_models {
+			if *model == Model::DeepQNetwork {
+				evictor.reload(network.clone())?;
+				reload_count += 1;
+			}
+		}
+
+		if let Some(bandit) = &mut self.bandit {
+			reload_count += bandit.reload(&network)?;
+		}
+
+		for shadow in &mut self.shadows {
+			if shadow.model() == Model::DeepQNetwork {
+				shadow.reload(network.clone())?;
+				reload_count += 1;
+			}
+		}
+
+		if reload_count == 0 {
+			return Err(Box::from("model must be reloadable"));
+		}
+
+		self.reloaded_network = reloaded_network;
+
+		info!("cache reloaded model from {}\n", path.unwrap_or("embedded model"));
+
+		Ok(())
+	}
+
+	// idle evictor learns current entries from least to most recently used, as if they arrived in that order
+	fn switch_model(self: &mut Self, model: Model) -> Result<()> {
+		let index: usize = self.idle_models.iter()
+			.position(|(idle_model, _): &(Model, Box<dyn Evictor + Send>)| *idle_model == model)
+			.ok_or_else(|| format!("model {} must be idle expert", model))?;
+		let (_, mut evictor): (Model, Box<dyn Evictor + Send>) = self.idle_models.swap_remove(index);
+		let mut entries: Vec<(&String, &Entry)> = self.entries.iter()
+			.collect::<Vec<(&String, &Entry)>>();
+
+		// retired evictor forgets entries without treating them as evicted, so it starts clean when chosen again
+		for (key, entry) in &entries {
+			self.model.on_remove(key, entry, false);
+		}
+
+		entries.reverse();
+
+		for (key, entry) in entries {
+			evictor.on_insert(key, entry);
+		}
+
+		if let Some(bandit) = &self.bandit {
+			info!("cache switched model from {} ({:.4}) to {} ({:.4})\n", self.active_model, bandit.score(self.active_model), model, bandit.score(model));
+		}
+
+		self.idle_models.push((self.active_model, replace(&mut self.model, evictor)));
+		self.active_model = model;
+
+		Ok(())
+	}
+
+	fn is_observed(self: &Self) -> bool {
+		self.experience.is_some() || self.trainer.is_some()
+	}
+
+	// reward 1 with next state on hit, reward 0 without next state on eviction
+	fn observe(self: &mut Self, state: [f32; FEATURE_COUNT], reward: f32, next_state: Option<[f32; FEATURE_COUNT]>) {
+		if let Some(experience) = &self.experience {
+			// stalled disk drops rows instead of stalling requests
+			match experience.try_send(Transition {
+				state: state,
+				reward: reward,
+				next_state: next_state
+			}) {
+				Ok(()) => {},
+				Err(TrySendError::Full(_)) => self.experience_drop_count += 1,
+				Err(TrySendError::Disconnected(_)) => {
+					warn!("cache stopped logging experiences\n");
+
+					self.experience = None;
+				}
+			}
+		}
+
+		if let Some(trainer) = &self.trainer {
+			// busy trainer drops transitions instead of stalling requests
+			if let Err(TrySendError::Disconnected(_)) = trainer.try_send(Transition {
+				state: state,
+				reward: reward,
+				next_state: next_state
+			}) {
+				warn!("cache stopped feeding trainer\n");
+
+				self.trainer = None;
+			}
+		}
+	}
+
+	pub fn train_with(self: &mut Self, trainer: SyncSender<Transition>) {
+		self.trainer = Some(trainer);
+	}
+
+	pub fn log_experience_with(self: &mut Self, experience: SyncSender<Transition>) {
+		self.experience = Some(experience);
+	}
+
+	pub fn replace_network(self: &mut Self, network: Perceptron) -> Result<()> {
+		self.model.replace_network(network)
+	}
+
+	// hit and miss counts since start, trainer compares windows of them
+	pub fn counts(self: &Self) -> (u64, u64) {
+		(self.hit_count, self.miss_count)
+	}
+
+	pub fn take_reloaded_network(self: &mut Self) -> Option<Perceptron> {
+		self.reloaded_network.take()
+	}
+
+	pub fn report(self: &Self) -> String {
+		let mut report: String = String::new();
+
+		let _ = write!(report, "model:{}\n", self.active_model);
+		let _ = write!(report, "entries:{}\nsize:{}\nhits:{}\nmisses:{}\nevictions:{}\nforced_evictions:{}\nrejections:{}\n", self.entries.len(), self.size, self.hit_count, self.miss_count, self.eviction_count, self.forced_eviction_count, self.rejection_count);
+
+		if self.experience.is_some() {
+			let _ = write!(report, "experience_drops:{}\n", self.experience_drop_count);
+		}
+
+		self.model.report(&mut report);
+
+		if let Some(bandit) = &self.bandit {
+			bandit.report(&mut report);
+		}
+
+		for shadow in &self.shadows {
+			shadow.report(&mut report);
+		}
+
+		report
+	}
+
 	pub fn remove(self: &mut Self, key: &str) -> bool {
+		for shadow in &mut self.shadows {
+			shadow.remove(key);
+		}
+
+		if let Some(bandit) = &mut self.bandit {
+			bandit.remove(key);
+		}
+
 		if let Some(entry) = self.entries.remove(key) {
+			self.size -= entry.size(key);
+			self.model.on_remove(key, &entry, false);
+
+			if let Some(admitter) = &mut self.admitter {
+				admitter.on_remove(key);
+			}
+
 			if ARGUMENT.is_verbose {
 				debug!("removed {:?}:{:#?} and became {:#?}\n", key, entry, self.entries);
 			}
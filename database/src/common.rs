@@ -4,8 +4,12 @@ use std::{
 	net::TcpStream,
 	process::exit,
 	result::Result as _Result,
-	sync::LazyLock,
-	time::{SystemTime, UNIX_EPOCH}
+	sync::{
+		atomic::{AtomicU64, Ordering},
+		Arc,
+		LazyLock
+	},
+	time::{Duration, SystemTime, UNIX_EPOCH}
 };
 use crate::{
 	argument::Argument,
@@ -16,7 +20,7 @@ pub type Job = Box<dyn FnOnce() + Send + 'static>;
 
 pub type Result<T, E = Box<dyn Error>> = _Result<T, E>;
 
-pub const ARGUMENT: LazyLock<Argument> = LazyLock::new(|| {
+pub static ARGUMENT: LazyLock<Argument> = LazyLock::new(|| {
 	match Argument::new() {
 		Ok(argument) => argument,
 		Err(error) => {
@@ -71,14 +75,94 @@ macro_rules! debug {
 //	}
 //}
 
+// xorshift64* generator, good enough for sampling decisions and not for anything secret
+pub struct Random {
+	state: u64
+}
+
+impl Random {
+	pub fn new() -> Self {
+		let seed: u64 = SystemTime::now().duration_since(UNIX_EPOCH)
+			.map(|duration: Duration| duration.as_nanos() as u64)
+			.unwrap_or(0);
+
+		Random {
+			state: seed | 1
+		}
+	}
+
+	pub fn next_u64(self: &mut Self) -> u64 {
+		self.state ^= self.state >> 12;
+		self.state ^= self.state << 25;
+		self.state ^= self.state >> 27;
+
+		self.state.wrapping_mul(0x2545F4914F6CDD1D)
+	}
+
+	// uniform in [0, 1)
+	pub fn next_f64(self: &mut Self) -> f64 {
+		(self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+	}
+}
+
 pub fn unix_epoch() -> Result<u64> {
 	Ok(SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs())
 }
 
+// entry access time in --time-unit, so recency keeps gaps finer than second as trace c_time does
+pub fn access_time() -> Result<u64> {
+	Ok((SystemTime::now().duration_since(UNIX_EPOCH)?.as_micros() * ARGUMENT.time_scale as u128 / 1_000_000) as u64)
+}
+
+// access time seen by evictors, replay advances manual one along trace c_time instead of wall clock
+#[derive(Clone)]
+pub enum AccessClock {
+	System,
+	Manual(Arc<AtomicU64>)
+}
+
+impl AccessClock {
+	pub fn now(self: &Self) -> Result<u64> {
+		Ok(match self {
+			AccessClock::System => access_time()?,
+			AccessClock::Manual(time) => time.load(Ordering::Relaxed)
+		})
+	}
+
+	pub fn set(self: &Self, now: u64) {
+		if let AccessClock::Manual(time) = self {
+			time.store(now, Ordering::Relaxed);
+		}
+	}
+}
+
+pub fn get_expiration(ttl: u32) -> Result<Option<u64>> {
+	Ok(if ttl == 0 {
+		None
+	} else {
+		Some(unix_epoch()? + ttl as u64)
+	})
+}
+
 pub fn log1p(x: u64) -> f32 {
 	(x as f64).ln_1p() as f32
 }
 
+pub fn parse_size(value: &str) -> Result<usize> {
+	let (number, multiplier): (&str, usize) = match value.as_bytes().last() {
+		Some(b'k' | b'K') => (&value[..value.len() - 1], 1 << 10),
+		Some(b'm' | b'M') => (&value[..value.len() - 1], 1 << 20),
+		Some(b'g' | b'G') => (&value[..value.len() - 1], 1 << 30),
+		_ => (value, 1)
+	};
+
+	if let Some(size) = number.parse::<usize>()?.checked_mul(multiplier) {
+		Ok(size)
+	} else {
+		Err(Box::from("size must be less than or equal to usize maximum"))
+	}
+}
+
 pub fn get_address(stream: &TcpStream) -> String {
 	if let Ok(address) = stream.peer_addr() {
 		address.to_string()
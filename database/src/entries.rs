@@ -0,0 +1,633 @@
+use std::{
+	collections::HashMap,
+	fmt::{Debug, Formatter, Result as _Result}
+};
+use crate::{
+	cache::Entry,
+	common::Random
+};
+
+const NIL: usize = usize::MAX;
+
+struct Slot {
+	key: String,
+	entry: Entry,
+	// recency list links, head is most recently used
+	previous: usize,
+	next: usize,
+	// frequency bucket membership, head is most recently moved into bucket
+	bucket: usize,
+	bucket_previous: usize,
+	bucket_next: usize,
+	// position in occupied
+	position: usize
+}
+
+// slots sharing same access_count, linked in ascending frequency order
+struct Bucket {
+	frequency: u64,
+	head: usize,
+	tail: usize,
+	previous: usize,
+	next: usize
+}
+
+pub struct Entries {
+	slots: Vec<Option<Slot>>,
+	vacancies: Vec<usize>,
+	// dense occupied slot indices, so sampling never lands on vacancies
+	occupied: Vec<usize>,
+	indices: HashMap<String, usize>,
+	head: usize,
+	tail: usize,
+	buckets: Vec<Option<Bucket>>,
+	bucket_vacancies: Vec<usize>,
+	lowest_bucket: usize
+}
+
+impl Entries {
+	pub fn with_capacity(capacity: usize) -> Entries {
+		Entries {
+			slots: Vec::with_capacity(capacity),
+			vacancies: Vec::new(),
+			occupied: Vec::with_capacity(capacity),
+			indices: HashMap::with_capacity(capacity),
+			head: NIL,
+			tail: NIL,
+			buckets: Vec::new(),
+			bucket_vacancies: Vec::new(),
+			lowest_bucket: NIL
+		}
+	}
+
+	pub fn len(self: &Self) -> usize {
+		self.indices.len()
+	}
+
+	pub fn get(self: &Self, key: &str) -> Option<&Entry> {
+		self.indices.get(key)
+			.map(|index: &usize| &self.slot(*index).entry)
+	}
+
+	pub fn get_key_value(self: &Self, key: &str) -> Option<(&String, &Entry)> {
+		self.indices.get(key)
+			.map(|index: &usize| {
+				let slot: &Slot = self.slot(*index);
+
+				(&slot.key, &slot.entry)
+			})
+	}
+
+	// picks min(count, len) distinct entries uniformly with floyd's algorithm over occupied slots
+	pub fn sample(self: &Self, random: &mut Random, count: usize) -> Vec<(&String, &Entry)> {
+		let length: usize = self.occupied.len();
+		let mut positions: Vec<usize> = Vec::with_capacity(count.min(length));
+
+		for j in length - count.min(length)..length {
+			let position: usize = (random.next_u64() % (j as u64 + 1)) as usize;
+
+			positions.push(if positions.contains(&position) {
+				j
+			} else {
+				position
+			});
+		}
+
+		positions.iter()
+			.map(|position: &usize| {
+				let slot: &Slot = self.slot(self.occupied[*position]);
+
+				(&slot.key, &slot.entry)
+			})
+			.collect::<Vec<(&String, &Entry)>>()
+	}
+
+	// access_count must only be changed through touch and age to keep buckets ordered
+	pub fn get_mut(self: &mut Self, key: &str) -> Option<&mut Entry> {
+		if let Some(index) = self.indices.get(key) {
+			let index: usize = *index;
+
+			Some(&mut self.slot_mut(index).entry)
+		} else {
+			None
+		}
+	}
+
+	pub fn insert(self: &mut Self, key: String, entry: Entry) {
+		self.remove(&key);
+
+		let frequency: u64 = entry.access_count;
+		let slot: Slot = Slot {
+			key: key.clone(),
+			entry: entry,
+			previous: NIL,
+			next: NIL,
+			bucket: NIL,
+			bucket_previous: NIL,
+			bucket_next: NIL,
+			position: self.occupied.len()
+		};
+		let index: usize = if let Some(index) = self.vacancies.pop() {
+			self.slots[index] = Some(slot);
+
+			index
+		} else {
+			self.slots.push(Some(slot));
+
+			self.slots.len() - 1
+		};
+
+		self.occupied.push(index);
+		self.indices.insert(key, index);
+		self.link(index);
+
+		let bucket: usize = self.find_bucket(frequency);
+
+		self.link_bucket(index, bucket);
+	}
+
+	pub fn remove(self: &mut Self, key: &str) -> Option<Entry> {
+		let index: usize = self.indices.remove(key)?;
+
+		self.unlink(index);
+		self.unlink_bucket(index);
+		self.vacancies.push(index);
+
+		let position: usize = self.slot(index).position;
+
+		self.occupied.swap_remove(position);
+
+		if let Some(moved) = self.occupied.get(position) {
+			let moved: usize = *moved;
+
+			self.slot_mut(moved).position = position;
+		}
+
+		self.slots[index].take()
+			.map(|slot: Slot| slot.entry)
+	}
+
+	// marks key as most recently used and counts one more access
+	pub fn touch(self: &mut Self, key: &str, accessed_at: u64) {
+		let index: usize = if let Some(index) = self.indices.get(key) {
+			*index
+		} else {
+			return;
+		};
+
+		if self.head != index {
+			self.unlink(index);
+			self.link(index);
+		}
+
+		let bucket: usize = self.slot(index).bucket;
+		let frequency: u64 = self.bucket(bucket).frequency + 1;
+		let next_bucket: usize = self.bucket(bucket).next;
+
+		let slot: &mut Slot = self.slot_mut(index);
+
+		slot.entry.accessed_at = accessed_at;
+		slot.entry.access_count = frequency;
+
+		// next bucket is created before current one may be emptied by unlinking
+		let next_bucket: usize = if next_bucket != NIL && self.bucket(next_bucket).frequency == frequency {
+			next_bucket
+		} else {
+			self.create_bucket(frequency, bucket, next_bucket)
+		};
+
+		self.unlink_bucket(index);
+		self.link_bucket(index, next_bucket);
+	}
+
+	// divides every access_count by 2 to the power of shift, keeping at least 1
+	pub fn age(self: &mut Self, shift: u32) {
+		if shift == 0 {
+			return;
+		}
+
+		let mut indices: Vec<usize> = Vec::with_capacity(self.len());
+		let mut bucket: usize = self.lowest_bucket;
+
+		// collected in ascending frequency and least recently moved order to keep ties stable
+		while bucket != NIL {
+			let mut index: usize = self.bucket(bucket).tail;
+
+			while index != NIL {
+				indices.push(index);
+				index = self.slot(index).bucket_previous;
+			}
+
+			bucket = self.bucket(bucket).next;
+		}
+
+		self.buckets.clear();
+		self.bucket_vacancies.clear();
+		self.lowest_bucket = NIL;
+
+		let mut highest_bucket: usize = NIL;
+
+		for index in indices {
+			let frequency: u64 = self.slot(index).entry.access_count
+				.checked_shr(shift)
+				.unwrap_or(0)
+				.max(1);
+
+			self.slot_mut(index).entry.access_count = frequency;
+
+			// frequencies stay ascending after shift, so only highest bucket may match
+			if highest_bucket == NIL || self.bucket(highest_bucket).frequency != frequency {
+				highest_bucket = self.create_bucket(frequency, highest_bucket, NIL);
+			}
+
+			self.link_bucket(index, highest_bucket);
+		}
+	}
+
+	pub fn least_recently_used(self: &Self) -> Option<&String> {
+		if self.tail == NIL {
+			None
+		} else {
+			Some(&self.slot(self.tail).key)
+		}
+	}
+
+	// least recently moved entry of lowest frequency bucket
+	pub fn least_frequently_used(self: &Self) -> Option<&String> {
+		if self.lowest_bucket == NIL {
+			None
+		} else {
+			Some(&self.slot(self.bucket(self.lowest_bucket).tail).key)
+		}
+	}
+
+	pub fn iter(self: &Self) -> Iter<'_> {
+		Iter {
+			entries: self,
+			index: self.head
+		}
+	}
+
+	fn slot(self: &Self, index: usize) -> &Slot {
+		self.slots[index].as_ref()
+			.expect("slot must be occupied")
+	}
+
+	fn slot_mut(self: &mut Self, index: usize) -> &mut Slot {
+		self.slots[index].as_mut()
+			.expect("slot must be occupied")
+	}
+
+	fn bucket(self: &Self, index: usize) -> &Bucket {
+		self.buckets[index].as_ref()
+			.expect("bucket must be occupied")
+	}
+
+	fn bucket_mut(self: &mut Self, index: usize) -> &mut Bucket {
+		self.buckets[index].as_mut()
+			.expect("bucket must be occupied")
+	}
+
+	fn link(self: &mut Self, index: usize) {
+		let head: usize = self.head;
+		let slot: &mut Slot = self.slot_mut(index);
+
+		slot.previous = NIL;
+		slot.next = head;
+
+		if head == NIL {
+			self.tail = index;
+		} else {
+			self.slot_mut(head).previous = index;
+		}
+
+		self.head = index;
+	}
+
+	fn unlink(self: &mut Self, index: usize) {
+		let (previous, next): (usize, usize) = {
+			let slot: &Slot = self.slot(index);
+
+			(slot.previous, slot.next)
+		};
+
+		if previous == NIL {
+			self.head = next;
+		} else {
+			self.slot_mut(previous).next = next;
+		}
+
+		if next == NIL {
+			self.tail = previous;
+		} else {
+			self.slot_mut(next).previous = previous;
+		}
+	}
+
+	// walks from lowest bucket, which is constant time for fresh entries counted once
+	fn find_bucket(self: &mut Self, frequency: u64) -> usize {
+		let mut previous: usize = NIL;
+		let mut bucket: usize = self.lowest_bucket;
+
+		while bucket != NIL && self.bucket(bucket).frequency < frequency {
+			previous = bucket;
+			bucket = self.bucket(bucket).next;
+		}
+
+		if bucket != NIL && self.bucket(bucket).frequency == frequency {
+			bucket
+		} else {
+			self.create_bucket(frequency, previous, bucket)
+		}
+	}
+
+	fn create_bucket(self: &mut Self, frequency: u64, previous: usize, next: usize) -> usize {
+		let bucket: Bucket = Bucket {
+			frequency: frequency,
+			head: NIL,
+			tail: NIL,
+			previous: previous,
+			next: next
+		};
+		let index: usize = if let Some(index) = self.bucket_vacancies.pop() {
+			self.buckets[index] = Some(bucket);
+
+			index
+		} else {
+			self.buckets.push(Some(bucket));
+
+			self.buckets.len() - 1
+		};
+
+		if previous == NIL {
+			self.lowest_bucket = index;
+		} else {
+			self.bucket_mut(previous).next = index;
+		}
+
+		if next != NIL {
+			self.bucket_mut(next).previous = index;
+		}
+
+		index
+	}
+
+	fn link_bucket(self: &mut Self, index: usize, bucket: usize) {
+		let head: usize = self.bucket(bucket).head;
+		let slot: &mut Slot = self.slot_mut(index);
+
+		slot.bucket = bucket;
+		slot.bucket_previous = NIL;
+		slot.bucket_next = head;
+
+		if head == NIL {
+			self.bucket_mut(bucket).tail = index;
+		} else {
+			self.slot_mut(head).bucket_previous = index;
+		}
+
+		self.bucket_mut(bucket).head = index;
+	}
+
+	fn unlink_bucket(self: &mut Self, index: usize) {
+		let (bucket, previous, next): (usize, usize, usize) = {
+			let slot: &Slot = self.slot(index);
+
+			(slot.bucket, slot.bucket_previous, slot.bucket_next)
+		};
+
+		if previous == NIL {
+			self.bucket_mut(bucket).head = next;
+		} else {
+			self.slot_mut(previous).bucket_next = next;
+		}
+
+		if next == NIL {
+			self.bucket_mut(bucket).tail = previous;
+		} else {
+			self.slot_mut(next).bucket_previous = previous;
+		}
+
+		if self.bucket(bucket).head == NIL {
+			let (previous_bucket, next_bucket): (usize, usize) = {
+				let bucket: &Bucket = self.bucket(bucket);
+
+				(bucket.previous, bucket.next)
+			};
+
+			if previous_bucket == NIL {
+				self.lowest_bucket = next_bucket;
+			} else {
+				self.bucket_mut(previous_bucket).next = next_bucket;
+			}
+
+			if next_bucket != NIL {
+				self.bucket_mut(next_bucket).previous = previous_bucket;
+			}
+
+			self.buckets[bucket] = None;
+			self.bucket_vacancies.push(bucket);
+		}
+	}
+}
+
+impl Debug for Entries {
+	fn fmt(self: &Self, formatter: &mut Formatter<'_>) -> _Result {
+		formatter.debug_map()
+			.entries(self.iter())
+			.finish()
+	}
+}
+
+// iterates from most recently used to least recently used
+pub struct Iter<'a> {
+	entries: &'a Entries,
+	index: usize
+}
+
+impl<'a> Iterator for Iter<'a> {
+	type Item = (&'a String, &'a Entry);
+
+	fn next(self: &mut Self) -> Option<Self::Item> {
+		if self.index == NIL {
+			return None;
+		}
+
+		let slot: &'a Slot = self.entries.slot(self.index);
+
+		self.index = slot.next;
+
+		Some((&slot.key, &slot.entry))
+	}
+}
+
+impl<'a> IntoIterator for &'a Entries {
+	type Item = (&'a String, &'a Entry);
+	type IntoIter = Iter<'a>;
+
+	fn into_iter(self: Self) -> Iter<'a> {
+		self.iter()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::{
+		cache::Entry,
+		common::Random
+	};
+	use super::{Bucket, Entries};
+
+	fn entry(access_count: u64) -> Entry {
+		Entry {
+			value: String::new(),
+			length: 0,
+			accessed_at: 0,
+			access_count: access_count,
+			expires_at: None,
+			cost: 0
+		}
+	}
+
+	fn keys(entries: &Entries) -> Vec<&str> {
+		entries.iter()
+			.map(|(key, _): (&String, &Entry)| key.as_str())
+			.collect::<Vec<&str>>()
+	}
+
+	fn insert(entries: &mut Entries, keys: &[(&str, u64)]) {
+		for (key, access_count) in keys {
+			entries.insert(key.to_string(), entry(*access_count));
+		}
+	}
+
+	#[test]
+	fn touches_to_front_and_next_bucket() {
+		let mut entries: Entries = Entries::with_capacity(4);
+
+		insert(&mut entries, &[("a", 1), ("b", 1), ("c", 1)]);
+		entries.touch("a", 5);
+
+		let touched: &Entry = entries.get("a").unwrap();
+
+		assert_eq!((touched.accessed_at, touched.access_count), (5, 2));
+		assert_eq!(keys(&entries), vec!["a", "c", "b"]);
+		assert_eq!(entries.least_recently_used().map(String::as_str), Some("b"));
+		assert_eq!(entries.least_frequently_used().map(String::as_str), Some("b"));
+
+		// missing key leaves order untouched
+		entries.touch("d", 6);
+
+		assert_eq!(keys(&entries), vec!["a", "c", "b"]);
+	}
+
+	#[test]
+	fn removes_and_reuses_slots() {
+		let mut entries: Entries = Entries::with_capacity(4);
+
+		insert(&mut entries, &[("a", 1), ("b", 3), ("c", 1)]);
+
+		assert_eq!(entries.remove("b").map(|entry: Entry| entry.access_count), Some(3));
+		assert!(entries.remove("b").is_none());
+		assert!(entries.get("b").is_none());
+		assert_eq!(keys(&entries), vec!["c", "a"]);
+
+		insert(&mut entries, &[("d", 1)]);
+
+		assert_eq!(entries.slots.len(), 3);
+		assert_eq!(entries.len(), 3);
+		assert_eq!(keys(&entries), vec!["d", "c", "a"]);
+
+		entries.remove("a");
+		entries.remove("d");
+
+		assert_eq!(keys(&entries), vec!["c"]);
+		assert_eq!(entries.least_recently_used().map(String::as_str), Some("c"));
+		assert_eq!(entries.least_frequently_used().map(String::as_str), Some("c"));
+
+		entries.remove("c");
+
+		assert!(entries.least_recently_used().is_none());
+		assert!(entries.least_frequently_used().is_none());
+	}
+
+	#[test]
+	fn samples_distinct_occupied_entries() {
+		let mut entries: Entries = Entries::with_capacity(8);
+		let mut random: Random = Random::new();
+
+		insert(&mut entries, &[("a", 1), ("b", 1), ("c", 1), ("d", 1), ("e", 1)]);
+		entries.remove("a");
+		entries.remove("c");
+
+		for count in 0..5 {
+			let mut samples: Vec<&str> = entries.sample(&mut random, count)
+				.into_iter()
+				.map(|(key, _): (&String, &Entry)| key.as_str())
+				.collect::<Vec<&str>>();
+
+			samples.sort();
+			samples.dedup();
+
+			assert_eq!(samples.len(), count.min(3));
+			assert!(samples.iter().all(|key: &&str| ["b", "d", "e"].contains(key)));
+		}
+	}
+
+	#[test]
+	fn breaks_ties_by_least_recently_moved() {
+		let mut entries: Entries = Entries::with_capacity(4);
+
+		insert(&mut entries, &[("a", 1), ("b", 1), ("c", 1)]);
+
+		assert_eq!(entries.least_recently_used().map(String::as_str), Some("a"));
+		assert_eq!(entries.least_frequently_used().map(String::as_str), Some("a"));
+
+		entries.touch("b", 1);
+		entries.touch("a", 2);
+
+		// c alone stays counted once
+		assert_eq!(entries.least_recently_used().map(String::as_str), Some("c"));
+		assert_eq!(entries.least_frequently_used().map(String::as_str), Some("c"));
+
+		entries.touch("c", 3);
+
+		// b reached count of 2 first, though c was used before a
+		assert_eq!(keys(&entries), vec!["c", "a", "b"]);
+		assert_eq!(entries.least_frequently_used().map(String::as_str), Some("b"));
+	}
+
+	#[test]
+	fn rebuilds_buckets_on_age() {
+		let mut entries: Entries = Entries::with_capacity(4);
+
+		insert(&mut entries, &[("a", 1), ("b", 4), ("c", 5), ("d", 8)]);
+		entries.age(1);
+
+		let access_counts: Vec<u64> = ["a", "b", "c", "d"].iter()
+			.map(|key: &&str| entries.get(key).unwrap().access_count)
+			.collect::<Vec<u64>>();
+
+		assert_eq!(access_counts, vec![1, 2, 2, 4]);
+		assert_eq!(entries.buckets.iter().filter(|bucket: &&Option<Bucket>| bucket.is_some()).count(), 3);
+		// recency is not changed by aging
+		assert_eq!(keys(&entries), vec!["d", "c", "b", "a"]);
+
+		entries.remove("a");
+
+		// b and c share bucket after halving, b came from lower one so it stays evicted first
+		assert_eq!(entries.least_frequently_used().map(String::as_str), Some("b"));
+
+		entries.touch("b", 1);
+
+		assert_eq!(entries.get("b").unwrap().access_count, 3);
+		assert_eq!(entries.least_frequently_used().map(String::as_str), Some("c"));
+
+		// shift past width floors every count at 1
+		entries.age(64);
+
+		assert!(entries.iter().all(|(_, entry): (&String, &Entry)| entry.access_count == 1));
+		assert_eq!(entries.buckets.iter().filter(|bucket: &&Option<Bucket>| bucket.is_some()).count(), 1);
+		assert_eq!(entries.least_frequently_used().map(String::as_str), Some("c"));
+	}
+}
\ No newline at end of file
@@ -0,0 +1,164 @@
+use std::{
+	fs::{File, OpenOptions, create_dir_all, read_dir, remove_file},
+	io::{Seek, SeekFrom, Write},
+	path::PathBuf,
+	sync::mpsc::{Receiver, RecvTimeoutError},
+	time::Duration
+};
+use crate::{
+	common::{ARGUMENT, Result},
+	feature::FEATURE_COUNT,
+	trainer::Transition,
+	debug,
+	error
+};
+
+// state, reward, next state and terminal flag per row
+const COLUMN_COUNT: usize = FEATURE_COUNT * 2 + 2;
+// fixed so row count can be rewritten in place, npy requires multiple of 64
+const HEADER_SIZE: usize = 128;
+const FLUSH_ROWS: usize = 256;
+
+// appends transitions shaped like DeepQNetworkAgent.store_experience to rotating npy files
+pub struct Experience {
+	root: PathBuf,
+	file: File,
+	index: u64,
+	row_count: usize,
+	rows_per_file: usize,
+	file_limit: usize,
+	buffer: Vec<u8>
+}
+
+impl Experience {
+	pub fn new(root: PathBuf, rows_per_file: usize, file_limit: usize) -> Result<Self> {
+		create_dir_all(&root)?;
+
+		let mut index: u64 = 0;
+
+		// never append to existing files, their rows may have been cut short by previous process
+		for file in read_dir(&root)? {
+			if let Some(file_index) = file?.path()
+				.file_stem()
+				.and_then(|stem| stem.to_str())
+				.and_then(|stem: &str| stem.parse::<u64>().ok()) {
+				index = index.max(file_index + 1);
+			}
+		}
+
+		Ok(Experience {
+			file: Experience::create(&root, index)?,
+			root: root,
+			index: index,
+			row_count: 0,
+			rows_per_file: rows_per_file,
+			file_limit: file_limit,
+			buffer: Vec::with_capacity(FLUSH_ROWS * COLUMN_COUNT * 4)
+		})
+	}
+
+	fn create(root: &PathBuf, index: u64) -> Result<File> {
+		let path: PathBuf = root.join(format!("{:010}.npy", index));
+
+		if ARGUMENT.is_verbose {
+			debug!("logging experiences to {:?}\n", path.display());
+		}
+
+		let mut file: File = OpenOptions::new()
+			.write(true)
+			.create_new(true)
+			.open(&path)?;
+
+		file.write_all(&Experience::header(0))?;
+
+		Ok(file)
+	}
+
+	fn header(row_count: usize) -> [u8; HEADER_SIZE] {
+		let mut header: [u8; HEADER_SIZE] = [b' '; HEADER_SIZE];
+		let dictionary: String = format!("{{'descr': '<f4', 'fortran_order': False, 'shape': ({}, {}), }}", row_count, COLUMN_COUNT);
+
+		header[..8].copy_from_slice(b"\x93NUMPY\x01\x00");
+		header[8..10].copy_from_slice(&((HEADER_SIZE - 10) as u16).to_le_bytes());
+		header[10..10 + dictionary.len()].copy_from_slice(dictionary.as_bytes());
+		header[HEADER_SIZE - 1] = b'\n';
+
+		header
+	}
+
+	// writes transitions sent by cache, flushing partial buffer whenever none arrive for a second
+	pub fn run(self: &mut Self, receiver: Receiver<Transition>) {
+		loop {
+			if let Err(error) = match receiver.recv_timeout(Duration::from_secs(1)) {
+				Ok(transition) => self.record(transition.state, transition.reward, transition.next_state),
+				Err(RecvTimeoutError::Timeout) => self.flush(),
+				Err(RecvTimeoutError::Disconnected) => return
+			} {
+				error!("{} from experience log\n", error);
+			}
+		}
+	}
+
+	// reward 1 with next state on hit, reward 0 without next state on eviction
+	fn record(self: &mut Self, state: [f32; FEATURE_COUNT], reward: f32, next_state: Option<[f32; FEATURE_COUNT]>) -> Result<()> {
+		let is_terminal: bool = next_state.is_none();
+
+		for value in state.iter()
+			.chain([reward].iter())
+			.chain(next_state.unwrap_or([0.0; FEATURE_COUNT]).iter())
+			.chain([is_terminal as u8 as f32].iter()) {
+			self.buffer.extend_from_slice(&value.to_le_bytes());
+		}
+
+		if self.buffer.len() >= FLUSH_ROWS * COLUMN_COUNT * 4 {
+			self.flush()?;
+		}
+
+		Ok(())
+	}
+
+	fn flush(self: &mut Self) -> Result<()> {
+		while self.buffer.len() != 0 {
+			let row_count: usize = (self.buffer.len() / (COLUMN_COUNT * 4)).min(self.rows_per_file - self.row_count);
+			let rows: Vec<u8> = self.buffer.drain(..row_count * COLUMN_COUNT * 4)
+				.collect::<Vec<u8>>();
+
+			self.file.seek(SeekFrom::End(0))?;
+			self.file.write_all(&rows)?;
+			self.row_count += row_count;
+
+			// rows are written before count so readers never see shape beyond data
+			self.file.seek(SeekFrom::Start(0))?;
+			self.file.write_all(&Experience::header(self.row_count))?;
+
+			if self.row_count == self.rows_per_file {
+				self.rotate()?;
+			}
+		}
+
+		Ok(())
+	}
+
+	fn rotate(self: &mut Self) -> Result<()> {
+		self.index += 1;
+		self.file = Experience::create(&self.root, self.index)?;
+		self.row_count = 0;
+
+		// oldest files beyond limit are dropped, including ones left by previous processes
+		if self.file_limit != 0 && self.index >= self.file_limit as u64 {
+			for file in read_dir(&self.root)? {
+				let path: PathBuf = file?.path();
+
+				if let Some(index) = path.file_stem()
+					.and_then(|stem| stem.to_str())
+					.and_then(|stem: &str| stem.parse::<u64>().ok()) {
+					if index + (self.file_limit as u64) <= self.index {
+						remove_file(&path)?;
+					}
+				}
+			}
+		}
+
+		Ok(())
+	}
+}
\ No newline at end of file
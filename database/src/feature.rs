@@ -0,0 +1,84 @@
+use crate::{
+	cache::Entry,
+	common::log1p
+};
+
+// columns of feature row fed to model per entry
+pub const FEATURE_COUNT: usize = 4;
+// bumped whenever feature layout or scaling changes, models may declare it in metadata
+pub const FEATURE_SCHEMA_VERSION: u32 = 1;
+
+// mirrors Environment.get_features of model/train.py, any change here must bump FEATURE_SCHEMA_VERSION
+pub fn get_features(current_time: u64, accessed_at: u64, access_count: u64, size: u64, capacity: u64) -> [f32; FEATURE_COUNT] {
+	[
+		log1p(current_time.saturating_sub(accessed_at)),
+		log1p(access_count),
+		log1p(size),
+		log1p(capacity)
+	]
+}
+
+pub struct FeatureExtractor {
+	capacity: u64
+}
+
+impl FeatureExtractor {
+	pub fn new(capacity: usize) -> Self {
+		FeatureExtractor {
+			capacity: capacity as u64
+		}
+	}
+
+	// now and accessed_at are access times in --time-unit, as c_time is in trace
+	pub fn extract(self: &Self, now: u64, entry: &Entry) -> [f32; FEATURE_COUNT] {
+		get_features(now, entry.accessed_at, entry.access_count, entry.length as u64, self.capacity)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::f32::consts::LN_2;
+	use crate::cache::Entry;
+	use super::{FeatureExtractor, get_features};
+
+	// (current_time, last_access_time, frequency, size, capacity) fed to Environment.get_features in float64, rounded to float32 as keras does
+	const GOLDEN_VECTORS: [((u64, u64, u64, u64, u64), [f32; 4]); 6] = [
+		((0, 0, 1, 1, 1), [0.0, LN_2, LN_2, LN_2]),
+		((100, 40, 3, 512, 128), [4.1108737, 1.3862944, 6.240276, 4.8598123]),
+		((40, 100, 2, 64, 128), [0.0, 1.0986123, 4.1743875, 4.8598123]),
+		((1700000000, 1699999000, 17, 1048576, 4096), [6.908755, 2.8903718, 13.862945, 8.31801]),
+		((5000000, 1000000, 1, 12, 256), [15.201805, LN_2, 2.5649493, 5.549076]),
+		((u64::MAX, 0, u64::MAX, 4294967295, u64::MAX), [44.36142, 44.36142, 22.18071, 44.36142])
+	];
+
+	// (now, accessed_at, access_count, length, capacity) in ms where given, value is left empty so only length can size entry
+	const EXTRACTED_VECTORS: [((u64, u64, u64, usize, usize), [f32; 4]); 4] = [
+		((1700000000123, 1700000000100, 3, 512, 128), [3.1780539, 1.3862944, 6.240276, 4.8598123]),
+		((1700000000100, 1700000000123, 1, 64, 64), [0.0, LN_2, 4.1743875, 4.1743875]),
+		((5000, 0, 1, 4096, 4096), [8.517393, LN_2, 8.31801, 8.31801]),
+		((1000000, 999000, 40, 1, 256), [6.908755, 3.713572, LN_2, 5.549076])
+	];
+
+	#[test]
+	fn matches_train_environment() {
+		for ((current_time, accessed_at, access_count, size, capacity), features) in GOLDEN_VECTORS {
+			assert_eq!(get_features(current_time, accessed_at, access_count, size, capacity), features, "features of {:?} diverged from train.py", (current_time, accessed_at, access_count, size, capacity));
+		}
+	}
+
+	#[test]
+	fn extracts_train_environment_features() {
+		for ((now, accessed_at, access_count, length, capacity), features) in EXTRACTED_VECTORS {
+			let entry: Entry = Entry {
+				value: String::new(),
+				length: length,
+				accessed_at: accessed_at,
+				access_count: access_count,
+				expires_at: None,
+				cost: 0
+			};
+
+			assert_eq!(FeatureExtractor::new(capacity).extract(now, &entry), features, "extracted features of {:?} diverged from train.py", (now, accessed_at, access_count, length, capacity));
+		}
+	}
+}
\ No newline at end of file
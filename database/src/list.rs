@@ -0,0 +1,207 @@
+use std::collections::HashMap;
+
+const NIL: usize = usize::MAX;
+
+struct Node {
+	key: String,
+	previous: usize,
+	next: usize
+}
+
+// ordered set of keys with constant time push, pop and removal, front is most recent
+pub struct List {
+	nodes: Vec<Option<Node>>,
+	vacancies: Vec<usize>,
+	indices: HashMap<String, usize>,
+	head: usize,
+	tail: usize
+}
+
+impl List {
+	pub fn new() -> List {
+		List {
+			nodes: Vec::new(),
+			vacancies: Vec::new(),
+			indices: HashMap::new(),
+			head: NIL,
+			tail: NIL
+		}
+	}
+
+	pub fn len(self: &Self) -> usize {
+		self.indices.len()
+	}
+
+	pub fn back(self: &Self) -> Option<&String> {
+		if self.tail == NIL {
+			None
+		} else {
+			Some(&self.node(self.tail).key)
+		}
+	}
+
+	// moves key to front if already present
+	pub fn push_front(self: &mut Self, key: &str) {
+		if let Some(index) = self.indices.get(key) {
+			let index: usize = *index;
+
+			if self.head != index {
+				self.unlink(index);
+				self.link(index);
+			}
+
+			return;
+		}
+
+		let node: Node = Node {
+			key: key.to_owned(),
+			previous: NIL,
+			next: NIL
+		};
+		let index: usize = if let Some(index) = self.vacancies.pop() {
+			self.nodes[index] = Some(node);
+
+			index
+		} else {
+			self.nodes.push(Some(node));
+
+			self.nodes.len() - 1
+		};
+
+		self.indices.insert(key.to_owned(), index);
+		self.link(index);
+	}
+
+	pub fn pop_back(self: &mut Self) -> Option<String> {
+		if self.tail == NIL {
+			return None;
+		}
+
+		let key: String = self.node(self.tail).key.clone();
+
+		self.remove(&key);
+
+		Some(key)
+	}
+
+	pub fn remove(self: &mut Self, key: &str) -> bool {
+		if let Some(index) = self.indices.remove(key) {
+			self.unlink(index);
+			self.nodes[index] = None;
+			self.vacancies.push(index);
+
+			true
+		} else {
+			false
+		}
+	}
+
+	fn node(self: &Self, index: usize) -> &Node {
+		self.nodes[index].as_ref()
+			.expect("node must be occupied")
+	}
+
+	fn node_mut(self: &mut Self, index: usize) -> &mut Node {
+		self.nodes[index].as_mut()
+			.expect("node must be occupied")
+	}
+
+	fn link(self: &mut Self, index: usize) {
+		let head: usize = self.head;
+		let node: &mut Node = self.node_mut(index);
+
+		node.previous = NIL;
+		node.next = head;
+
+		if head == NIL {
+			self.tail = index;
+		} else {
+			self.node_mut(head).previous = index;
+		}
+
+		self.head = index;
+	}
+
+	fn unlink(self: &mut Self, index: usize) {
+		let (previous, next): (usize, usize) = {
+			let node: &Node = self.node(index);
+
+			(node.previous, node.next)
+		};
+
+		if previous == NIL {
+			self.head = next;
+		} else {
+			self.node_mut(previous).next = next;
+		}
+
+		if next == NIL {
+			self.tail = previous;
+		} else {
+			self.node_mut(next).previous = previous;
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::List;
+
+	fn keys(list: &List) -> Vec<String> {
+		let mut keys: Vec<String> = Vec::new();
+		let mut index: usize = list.head;
+
+		while let Some(node) = list.nodes.get(index).and_then(Option::as_ref) {
+			keys.push(node.key.clone());
+			index = node.next;
+		}
+
+		keys
+	}
+
+	#[test]
+	fn pushes_to_front_and_pops_back() {
+		let mut list: List = List::new();
+
+		list.push_front("a");
+		list.push_front("b");
+		list.push_front("c");
+
+		assert_eq!(keys(&list), vec!["c", "b", "a"]);
+		assert_eq!(list.back().map(String::as_str), Some("a"));
+
+		// present key only moves to front
+		list.push_front("a");
+
+		assert_eq!(list.len(), 3);
+		assert_eq!(keys(&list), vec!["a", "c", "b"]);
+		assert_eq!(list.pop_back(), Some("b".to_string()));
+		assert_eq!(list.pop_back(), Some("c".to_string()));
+		assert_eq!(list.pop_back(), Some("a".to_string()));
+		assert_eq!(list.pop_back(), None);
+		assert!(list.back().is_none());
+	}
+
+	#[test]
+	fn removes_and_reuses_nodes() {
+		let mut list: List = List::new();
+
+		list.push_front("a");
+		list.push_front("b");
+		list.push_front("c");
+
+		assert!(list.remove("b"));
+		assert!(!list.remove("b"));
+		assert_eq!(keys(&list), vec!["c", "a"]);
+
+		list.push_front("d");
+
+		assert_eq!(list.nodes.len(), 3);
+		assert_eq!(keys(&list), vec!["d", "c", "a"]);
+
+		assert!(list.remove("d"));
+		assert!(list.remove("a"));
+		assert_eq!(keys(&list), vec!["c"]);
+		assert_eq!(list.back().map(String::as_str), Some("c"));
+	}
+}
\ No newline at end of file
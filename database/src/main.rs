@@ -1,16 +1,31 @@
+mod admission;
 mod argument;
+mod bandit;
 mod cache;
 mod common;
+mod entries;
+mod experience;
+mod feature;
+mod list;
 mod model;
+mod network;
+mod perceptron;
 mod protocol;
+mod replay;
+mod shadow;
+mod signal;
 mod storage;
 mod thread_pool;
+mod trainer;
 mod logger;
 
 use std::{
 	io::{Error, ErrorKind, IoSlice, Read, Write},
 	net::{TcpListener, TcpStream},
+	path::PathBuf,
 	sync::{
+		atomic::{AtomicBool, Ordering},
+		mpsc::{Receiver, SyncSender, sync_channel},
 		Arc,
 		Mutex,
 		MutexGuard,
@@ -19,40 +34,190 @@ use std::{
 		RwLockReadGuard,
 		RwLockWriteGuard
 	},
-	thread::available_parallelism,
-	time::Duration
+	thread::{available_parallelism, sleep, spawn, yield_now},
+	time::{Duration, Instant}
 };
 
 use crate::{
-	cache::{Cache, Entry},
-	common::{ARGUMENT, Result, get_address},
+	cache::{Cache, Candidates, Entry, Model},
+	common::{ARGUMENT, Result, get_address, get_expiration, unix_epoch},
+	experience::Experience,
 	protocol::{
 		OPERATION_DEL,
+		OPERATION_EXPIRE,
 		OPERATION_GET,
 		OPERATION_HELLO,
 		OPERATION_NOP,
 		OPERATION_OK,
+		OPERATION_PERSIST,
 		OPERATION_QUIT,
 		OPERATION_READY,
+		OPERATION_RELOAD,
 		OPERATION_SET,
-		OPERATION_VALUE,
+		OPERATION_STATS,
+		OPERATION_TTL,
 		Version,
 		read_string,
-		send_error
+		read_u32,
+		send_error,
+		send_number,
+		send_value
 	},
-	storage::Storage,
-	thread_pool::ThreadPool
+	network::{Backend, Network, check_model_path, load_network, read_model},
+	perceptron::Perceptron,
+	replay::replay,
+	signal::{is_hung_up, listen_hangup},
+	storage::{EXPERIENCE_FOLDER, Storage, check_key},
+	thread_pool::ThreadPool,
+	trainer::{Trainer, Transition}
 };
 
 fn main() {
 	if let Err(error) = (|| -> Result<()> {
 		info!("starting dQache {} on {}\n", ARGUMENT.version, ARGUMENT.platform);
 
-		let cache: Arc<Mutex<Cache>> = Arc::new(Mutex::new(Cache::new(ARGUMENT.model, ARGUMENT.capacity)?));
+		if let Some(trace) = &ARGUMENT.replay {
+			return replay(trace);
+		}
+
+		let cache: Arc<Mutex<Cache>> = Arc::new(Mutex::new(Cache::new(ARGUMENT.model, ARGUMENT.capacity, ARGUMENT.maximum_memory)?));
 		let storage: Arc<RwLock<Storage>> = Arc::new(RwLock::new(Storage::new(&ARGUMENT.directory)?));
 		let thread_pool: ThreadPool = ThreadPool::new(available_parallelism()?.get() * 2)?;
 		let listener: TcpListener = TcpListener::bind((ARGUMENT.host, ARGUMENT.port))?;
 
+		if ARGUMENT.is_online_trained {
+			if ARGUMENT.is_automatic || !matches!(ARGUMENT.model, Model::DeepQNetwork) || !matches!(ARGUMENT.backend, Backend::Rust) {
+				return Err(Box::from("online training must use dqn model with rust backend"));
+			}
+
+			// bounded so stalled trainer costs dropped transitions instead of memory
+			let (sender, receiver): (SyncSender<Transition>, Receiver<Transition>) = sync_channel(4096);
+			let mut trainer: Trainer = Trainer::new(Perceptron::from_onnx(&read_model(ARGUMENT.model_path.as_deref())?)?);
+			let trainer_cache: Arc<Mutex<Cache>> = cache.clone();
+
+			cache.lock()
+				.map_err(|error: PoisonError<MutexGuard<'_, Cache>>| error.to_string())?
+				.train_with(sender);
+
+			spawn(move || trainer.run(trainer_cache, receiver, Duration::from_secs(ARGUMENT.training_interval)));
+
+			info!("training model online, publishing every {} seconds\n", ARGUMENT.training_interval);
+		}
+
+		if ARGUMENT.is_experience_logged {
+			let root: PathBuf = PathBuf::from(&ARGUMENT.directory).join(EXPERIENCE_FOLDER);
+			let mut experience: Experience = Experience::new(root.clone(), ARGUMENT.experience_rows, ARGUMENT.experience_files)?;
+			// bounded so stalled disk costs dropped rows instead of memory
+			let (sender, receiver): (SyncSender<Transition>, Receiver<Transition>) = sync_channel(1 << 16);
+
+			cache.lock()
+				.map_err(|error: PoisonError<MutexGuard<'_, Cache>>| error.to_string())?
+				.log_experience_with(sender);
+
+			spawn(move || experience.run(receiver));
+
+			info!("logging experiences to {:?}\n", root.display());
+		}
+
+		let sweeper_cache: Arc<Mutex<Cache>> = cache.clone();
+		let sweeper_storage: Arc<RwLock<Storage>> = storage.clone();
+
+		spawn(move || loop {
+			sleep(Duration::from_secs(ARGUMENT.sweep_interval));
+
+			if let Err(error) = (|| -> Result<()> {
+				let keys: Vec<String> = sweeper_storage.write()
+					.map_err(|error: PoisonError<RwLockWriteGuard<'_, Storage>>| error.to_string())?
+					.sweep()?;
+
+				if keys.len() != 0 {
+					let mut cache: MutexGuard<'_, Cache> = sweeper_cache.lock()
+						.map_err(|error: PoisonError<MutexGuard<'_, Cache>>| error.to_string())?;
+
+					for key in &keys {
+						cache.remove(key);
+					}
+
+					if ARGUMENT.is_verbose {
+						debug!("swept {} expired keys\n", keys.len());
+					}
+				}
+
+				Ok(())
+			})() {
+				error!("{} from sweeper\n", error);
+			}
+		});
+
+		if ARGUMENT.is_background_evicted {
+			let evictor_cache: Arc<Mutex<Cache>> = cache.clone();
+			let is_eviction_due: Arc<AtomicBool> = cache.lock()
+				.map_err(|error: PoisonError<MutexGuard<'_, Cache>>| error.to_string())?
+				.eviction_due();
+
+			spawn(move || loop {
+				if !is_eviction_due.load(Ordering::Relaxed) {
+					sleep(Duration::from_millis(ARGUMENT.eviction_interval));
+
+					continue;
+				}
+
+				// lock is released while candidates are scored, so requests only wait for sampling and evicting
+				if let Err(error) = (|| -> Result<()> {
+					let candidates: Option<Candidates> = evictor_cache.lock()
+						.map_err(|error: PoisonError<MutexGuard<'_, Cache>>| error.to_string())?
+						.sample_eviction()?;
+
+					if let Some(candidates) = candidates {
+						match candidates.score() {
+							Ok((keys, scores)) => evictor_cache.lock()
+								.map_err(|error: PoisonError<MutexGuard<'_, Cache>>| error.to_string())?
+								.finish_eviction(keys, &scores)?,
+							// evicting under lock lets fallback see failure and take over
+							Err(error) => {
+								error!("{} from background scoring\n", error);
+
+								evictor_cache.lock()
+									.map_err(|error: PoisonError<MutexGuard<'_, Cache>>| error.to_string())?
+									.evict_batch()?;
+							}
+						}
+					}
+
+					Ok(())
+				})() {
+					error!("{} from background evictor\n", error);
+					sleep(Duration::from_millis(ARGUMENT.eviction_interval));
+				}
+
+				// more batches may be due, yielding lets waiting requests take lock first
+				yield_now();
+			});
+		}
+
+		let reloader_cache: Arc<Mutex<Cache>> = cache.clone();
+
+		listen_hangup()?;
+
+		spawn(move || loop {
+			sleep(Duration::from_secs(1));
+
+			if !is_hung_up() {
+				continue;
+			}
+
+			if let Err(error) = (|| -> Result<()> {
+				// slow loading and dry run happen before lock, which is held only for swap
+				let network: Box<dyn Network + Send> = load_network(ARGUMENT.model_path.as_deref(), ARGUMENT.backend)?;
+
+				reloader_cache.lock()
+					.map_err(|error: PoisonError<MutexGuard<'_, Cache>>| error.to_string())?
+					.reload(network, ARGUMENT.model_path.as_deref())
+			})() {
+				error!("{} from hangup reload\n", error);
+			}
+		});
+
 		info!("lisening on 0.0.0.0:{} with {} threads\n", ARGUMENT.port, thread_pool.size());
 
 		for stream in listener.incoming() {
@@ -66,7 +231,7 @@ fn main() {
 			thread_pool.execute(move || {
 				let mut double_word: [u8; 4] = [0; 4];
 
-				if let Err(error) = (|| -> Result<()> {
+				let version: Version = match (|| -> Result<Version> {
 					stream.write_vectored(&[
 						IoSlice::new(OPERATION_READY),
 						IoSlice::new(&ARGUMENT.version.as_bytes())
@@ -85,18 +250,21 @@ fn main() {
 						}
 
 						info!("client connected with {} from {}\n", version, stream.peer_addr()?);
-					} else {
-						return Err(Box::from("client version must be invalid\n"));
-					}
 
-					stream.write(OPERATION_OK)?;
+						stream.write(OPERATION_OK)?;
 
-					Ok(())
+						Ok(version)
+					} else {
+						Err(Box::from("client version must be invalid\n"))
+					}
 				})() {
-					let _ = send_error(&mut stream, &mut double_word, error.to_string());
+					Ok(version) => version,
+					Err(error) => {
+						let _ = send_error(&mut stream, &mut double_word, error.to_string());
 
-					return;
-				}
+						return;
+					}
+				};
 
 				let mut byte: [u8; 1] = [0];
 
@@ -108,19 +276,29 @@ fn main() {
 							OPERATION_SET => {
 								let key: String = read_string::<1>(&mut stream, &mut byte)?;
 								let value: String = read_string::<4>(&mut stream, &mut double_word)?;
+								let expires_at: Option<u64> = if version >= Version::new(0, 0, 2) {
+									get_expiration(read_u32(&mut stream, &mut double_word)?)?
+								} else {
+									None
+								};
+
+								// checked once request is fully read, so rejection leaves stream in sync
+								check_key(&key)?;
 
 								cache.lock()
 									.map_err(|error: PoisonError<MutexGuard<'_, Cache>>| error.to_string())?
-									.set(&key, Entry::new(&value)?)?;
+									.set(&key, Entry::new(&value, expires_at)?)?;
 								storage.write()
 									.map_err(|error: PoisonError<RwLockWriteGuard<'_, Storage>>| error.to_string())?
-									.write(&key, value)?;
+									.write(&key, value, expires_at)?;
 
 								stream.write(OPERATION_OK)?;
 							},
 							OPERATION_DEL => {
 								let key: String = read_string::<1>(&mut stream, &mut byte)?;
 
+								check_key(&key)?;
+
 								cache.lock()
 									.map_err(|error: PoisonError<MutexGuard<'_, Cache>>| error.to_string())?
 									.remove(&key);
@@ -135,38 +313,120 @@ fn main() {
 							},
 							OPERATION_GET => {
 								let key: String = read_string::<1>(&mut stream, &mut byte)?;
-								let (is_cached, value): (bool, String) = if let Some(entry) = cache.lock()
+
+								check_key(&key)?;
+
+								let (value, entry): (String, Option<Entry>) = if let Some(entry) = cache.lock()
 									.map_err(|error: PoisonError<MutexGuard<'_, Cache>>| error.to_string())?
 									.get(&key)? {
-									(true, entry.value.clone())
+									(entry.value.clone(), None)
 								} else {
-									if let Some(value) = storage.read()
-										.map_err(|error: PoisonError<RwLockReadGuard<'_, Storage>>| error.to_string())?
-										.read(&key)? {
-											(false, value)
-										} else {
-											return Err(Box::from("key must exist"));
-										}
+									let storage: RwLockReadGuard<'_, Storage> = storage.read()
+										.map_err(|error: PoisonError<RwLockReadGuard<'_, Storage>>| error.to_string())?;
+									let started_at: Instant = Instant::now();
+
+									if let Some(value) = storage.read(&key)? {
+										let mut entry: Entry = Entry::new(&value, storage.expiration(&key))?;
+
+										// fetch latency lets cost aware models keep expensive values longer
+										entry.cost = started_at.elapsed().as_micros() as u64;
+
+										(value, Some(entry))
+									} else {
+										return Err(Box::from("key must exist"));
+									}
 								};
 
-								if !is_cached {
+								if let Some(entry) = entry {
 									cache.lock()
 										.map_err(|error: PoisonError<MutexGuard<'_, Cache>>| error.to_string())?
-										.set(&key, Entry::new(&value)?)?;
+										.fill(&key, entry)?;
+								}
+
+								send_value(&mut stream, &mut double_word, &value)?;
+							},
+							OPERATION_EXPIRE => {
+								let key: String = read_string::<1>(&mut stream, &mut byte)?;
+								let ttl: u32 = read_u32(&mut stream, &mut double_word)?;
+
+								check_key(&key)?;
+
+								if ttl == 0 {
+									return Err(Box::from("ttl must be greater than 0"));
+								}
+
+								let expires_at: Option<u64> = get_expiration(ttl)?;
+
+								if !storage.write()
+									.map_err(|error: PoisonError<RwLockWriteGuard<'_, Storage>>| error.to_string())?
+									.expire(&key, expires_at)? {
+									return Err(Box::from("key must exist"));
+								}
+
+								cache.lock()
+									.map_err(|error: PoisonError<MutexGuard<'_, Cache>>| error.to_string())?
+									.expire(&key, expires_at);
+
+								stream.write(OPERATION_OK)?;
+							},
+							OPERATION_TTL => {
+								let key: String = read_string::<1>(&mut stream, &mut byte)?;
+
+								check_key(&key)?;
+
+								let storage: RwLockReadGuard<'_, Storage> = storage.read()
+									.map_err(|error: PoisonError<RwLockReadGuard<'_, Storage>>| error.to_string())?;
+
+								if !storage.contains(&key)? {
+									return Err(Box::from("key must exist"));
+								}
+
+								let ttl: u64 = if let Some(expires_at) = storage.expiration(&key) {
+									expires_at.saturating_sub(unix_epoch()?).max(1)
+								} else {
+									0
+								};
+
+								drop(storage);
+
+								send_number(&mut stream, &mut double_word, ttl.min(u32::MAX as u64) as u32)?;
+							},
+							OPERATION_PERSIST => {
+								let key: String = read_string::<1>(&mut stream, &mut byte)?;
+
+								check_key(&key)?;
+
+								if !storage.write()
+									.map_err(|error: PoisonError<RwLockWriteGuard<'_, Storage>>| error.to_string())?
+									.expire(&key, None)? {
+									return Err(Box::from("key must exist"));
 								}
 
-								let value_length: usize = value.len();
+								cache.lock()
+									.map_err(|error: PoisonError<MutexGuard<'_, Cache>>| error.to_string())?
+									.expire(&key, None);
+
+								stream.write(OPERATION_OK)?;
+							},
+							OPERATION_RELOAD => {
+								let path: String = read_string::<4>(&mut stream, &mut double_word)?;
+
+								check_model_path(&path)?;
 
-								double_word[0] = (value_length >> 24) as u8;
-								double_word[1] = (value_length >> 16) as u8;
-								double_word[2] = (value_length >> 8) as u8;
-								double_word[3] = value_length as u8;
+								let network: Box<dyn Network + Send> = load_network(Some(&path), ARGUMENT.backend)?;
+
+								cache.lock()
+									.map_err(|error: PoisonError<MutexGuard<'_, Cache>>| error.to_string())?
+									.reload(network, Some(&path))?;
+
+								stream.write(OPERATION_OK)?;
+							},
+							OPERATION_STATS => {
+								let report: String = cache.lock()
+									.map_err(|error: PoisonError<MutexGuard<'_, Cache>>| error.to_string())?
+									.report();
 
-								stream.write_vectored(&[
-									IoSlice::new(OPERATION_VALUE),
-									IoSlice::new(&double_word),
-									IoSlice::new(value.as_bytes())
-								])?;
+								send_value(&mut stream, &mut double_word, &report)?;
 							},
 							OPERATION_NOP => {
 								stream.write(OPERATION_OK)?;
@@ -1,124 +1,833 @@
-use ort::{
-	session::{
-		builder::GraphOptimizationLevel,
-		InMemorySession,
-		Session,
-		SessionOutputs
-	},
-	value::Value
-};
 use std::{
-	collections::HashMap,
-	iter::zip
+	collections::{BTreeMap, HashMap},
+	fmt::Write,
+	iter::zip,
+	sync::{Arc, Mutex, MutexGuard, PoisonError},
+	time::{Duration, Instant}
 };
 use crate::{
-	cache::{Entry, Evictor},
-	common::{ARGUMENT, Result, log1p, unix_epoch},
-	debug
+	cache::{Candidates, Entry, Evictor},
+	common::{ARGUMENT, AccessClock, Random, Result, unix_epoch},
+	entries::Entries,
+	feature::{FEATURE_COUNT, FeatureExtractor},
+	list::List,
+	network::{Backend, Network, load_network},
+	perceptron::Perceptron,
+	debug,
+	warn
 };
 
-pub struct DeepQNetwork<'a> {
-	model: InMemorySession<'a>
+pub struct DeepQNetwork {
+	// shared with background evictor, which scores candidates outside cache lock
+	model: Arc<Mutex<Box<dyn Network + Send>>>,
+	backend: Backend,
+	features: FeatureExtractor,
+	clock: AccessClock,
+	sample_count: usize,
+	// lowest scored candidates of previous sampling, rescored with next samples
+	pool: Vec<String>,
+	pool_size: usize,
+	random: Random
 }
 
-impl<'a> DeepQNetwork<'a> {
-	pub fn new() -> Result<Self> {
+impl DeepQNetwork {
+	pub fn new(path: Option<&str>, backend: Backend, capacity: usize, sample_count: usize, pool_size: usize, clock: AccessClock) -> Result<Self> {
 		Ok(DeepQNetwork {
-			model: Session::builder()?
-				.with_optimization_level(GraphOptimizationLevel::Level3)?
-				.commit_from_memory_directly(include_bytes!("../model.onnx"))?
+			model: Arc::new(Mutex::new(load_network(path, backend)?)),
+			backend: backend,
+			features: FeatureExtractor::new(capacity),
+			clock: clock,
+			sample_count: sample_count,
+			pool: Vec::with_capacity(pool_size),
+			pool_size: pool_size,
+			random: Random::new()
 		})
 	}
 }
 
-impl<'a> Evictor for DeepQNetwork<'a> {
-	fn select_victim(self: &mut Self, entries: &HashMap<String, Entry>) -> Result<String> {
-		let length: usize = entries.len();
+impl Evictor for DeepQNetwork {
+	// scoring already running outside lock finishes on network it started with
+	fn reload(self: &mut Self, network: Arc<Mutex<Box<dyn Network + Send>>>) -> Result<()> {
+		self.model = network;
+		self.pool.clear();
 
-		if length == 0 {
-			return Err(Box::from("entries length must be greater than 0"));
+		Ok(())
+	}
+
+	fn replace_network(self: &mut Self, network: Perceptron) -> Result<()> {
+		if matches!(self.backend, Backend::Rust) {
+			self.model = Arc::new(Mutex::new(Box::new(network)));
+
+			Ok(())
+		} else {
+			Err(Box::from("model must use rust backend to be trained"))
 		}
+	}
+
+	fn select_victim(self: &mut Self, entries: &Entries) -> Result<String> {
+		self.select_victims(entries, 1)?
+			.pop()
+			.ok_or_else(|| Box::from("victims must not be empty"))
+	}
 
-		let mut keys: Vec<&String> = Vec::with_capacity(length);
-		let mut inputs: Vec<f32> = Vec::with_capacity(length * 4);
-		let capacity: f32 = log1p(entries.capacity() as u64);
+	// single inference ranks all candidates, so lowest count of them are evicted together
+	fn select_victims(self: &mut Self, entries: &Entries, count: usize) -> Result<Vec<String>> {
+		let candidates: Candidates = self.sample_candidates(entries)?
+			.ok_or("candidates must be sampled")?;
+		let length: usize = candidates.keys.len();
+		let output: Vec<f32> = self.model.lock()
+			.map_err(|error: PoisonError<MutexGuard<'_, Box<dyn Network + Send>>>| error.to_string())?
+			.run(candidates.inputs, length)?;
 
-		for entry in entries {
-			keys.push(entry.0);
-			inputs.push(log1p(unix_epoch()? - entry.1.accessed_at));
-			inputs.push(log1p(entry.1.access_count));
-			inputs.push(log1p(entry.1.value.len() as u64));
-			inputs.push(capacity);
+		Ok(self.rank_candidates(candidates.keys, &output, count))
+	}
+
+	fn sample_candidates(self: &mut Self, entries: &Entries) -> Result<Option<Candidates>> {
+		if entries.len() == 0 {
+			return Err(Box::from("entries length must be greater than 0"));
 		}
 
-		let output: SessionOutputs = self.model.run(vec![("args_0", Value::from_array((([length, 4]), inputs))?)])?;
-		let output: &[f32] = output[0].try_extract_tensor::<f32>()?.1;
+		let is_sampled: bool = self.sample_count != 0 && self.sample_count + self.pool.len() < entries.len();
+		let candidates: Vec<(&String, &Entry)> = if is_sampled {
+			let mut candidates: Vec<(&String, &Entry)> = entries.sample(&mut self.random, self.sample_count);
+
+			for key in &self.pool {
+				if let Some(candidate) = entries.get_key_value(key) {
+					if !candidates.iter().any(|(key, _): &(&String, &Entry)| *key == candidate.0) {
+						candidates.push(candidate);
+					}
+				}
+			}
+
+			candidates
+		} else {
+			entries.iter()
+				.collect::<Vec<(&String, &Entry)>>()
+		};
+
+		let mut keys: Vec<String> = Vec::with_capacity(candidates.len());
+		let mut inputs: Vec<f32> = Vec::with_capacity(candidates.len() * FEATURE_COUNT);
+		let now: u64 = self.clock.now()?;
 
-		let mut i: usize = 0;
-		let mut minimum_score: f32 = f32::MAX;
-		let mut minimum_index: usize = 0;
+		for entry in &candidates {
+			keys.push(entry.0.clone());
+			inputs.extend(self.features.extract(now, entry.1));
+		}
+
+		Ok(Some(Candidates {
+			keys: keys,
+			inputs: inputs,
+			network: self.model.clone()
+		}))
+	}
 
+	fn rank_candidates(self: &mut Self, keys: Vec<String>, scores: &[f32], count: usize) -> Vec<String> {
 		if ARGUMENT.is_verbose {
-			let mut key_scores: Vec<(&&String, &f32)> = zip(&keys, output).collect::<Vec<(&&String, &f32)>>();
+			let mut key_scores: Vec<(&String, &f32)> = zip(&keys, scores).collect::<Vec<(&String, &f32)>>();
 
-			key_scores.sort_by(|a: &(&&String, &f32), b: &(&&String, &f32)| a.1.total_cmp(b.1));
+			key_scores.sort_by(|a: &(&String, &f32), b: &(&String, &f32)| a.1.total_cmp(b.1));
 
 			debug!("scored with {:#?}\n", key_scores);
 		}
 
-		for score in output {
-			if *score < minimum_score {
-				minimum_score = *score;
-				minimum_index = i;
+		let mut indices: Vec<usize> = (0..keys.len()).collect::<Vec<usize>>();
+
+		// stable sort keeps earlier candidate on ties, as single victim selection did
+		indices.sort_by(|a: &usize, b: &usize| scores[*a].total_cmp(&scores[*b]));
+
+		let count: usize = count.max(1).min(keys.len());
+
+		// pool is only read back when sampling, so refreshing it after full scan is harmless
+		if self.sample_count != 0 && self.pool_size != 0 {
+			self.pool = indices[count..].iter()
+				.take(self.pool_size)
+				.map(|index: &usize| keys[*index].clone())
+				.collect::<Vec<String>>();
+		}
+
+		indices[..count].iter()
+			.map(|index: &usize| keys[*index].clone())
+			.collect::<Vec<String>>()
+	}
+
+	// candidate enters only when scored at least as worth keeping as victim
+	fn admit(self: &mut Self, candidate: &Entry, victim: &Entry) -> Result<bool> {
+		let now: u64 = self.clock.now()?;
+		let mut inputs: Vec<f32> = Vec::with_capacity(2 * FEATURE_COUNT);
+
+		inputs.extend(self.features.extract(now, candidate));
+		inputs.extend(self.features.extract(now, victim));
+
+		let output: Vec<f32> = self.model.lock()
+			.map_err(|error: PoisonError<MutexGuard<'_, Box<dyn Network + Send>>>| error.to_string())?
+			.run(inputs, 2)?;
+
+		if ARGUMENT.is_verbose {
+			debug!("scored candidate {} against victim {}\n", output[0], output[1]);
+		}
+
+		Ok(output[0] >= output[1])
+	}
+}
+
+// hands eviction to fallback policy for cooldown whenever primary fails or exceeds latency budget
+pub struct Fallback {
+	primary: Box<dyn Evictor + Send>,
+	fallback: Box<dyn Evictor + Send>,
+	budget: Option<Duration>,
+	cooldown: u64,
+	// primary is retried once unix epoch reaches it
+	retry_at: u64,
+	failure_count: u64,
+	slow_count: u64,
+	fallback_count: u64
+}
+
+impl Fallback {
+	pub fn new(primary: Box<dyn Evictor + Send>, fallback: Box<dyn Evictor + Send>, budget: Option<Duration>, cooldown: u64) -> Self {
+		Fallback {
+			primary: primary,
+			fallback: fallback,
+			budget: budget,
+			cooldown: cooldown,
+			retry_at: 0,
+			failure_count: 0,
+			slow_count: 0,
+			fallback_count: 0
+		}
+	}
+}
+
+impl Evictor for Fallback {
+	fn select_victim(self: &mut Self, entries: &Entries) -> Result<String> {
+		self.select_victims(entries, 1)?
+			.pop()
+			.ok_or_else(|| Box::from("victims must not be empty"))
+	}
+
+	fn select_victims(self: &mut Self, entries: &Entries, count: usize) -> Result<Vec<String>> {
+		let now: u64 = unix_epoch()?;
+
+		if now >= self.retry_at {
+			let started_at: Instant = Instant::now();
+
+			match self.primary.select_victims(entries, count) {
+				Ok(victims) => {
+					if let Some(budget) = self.budget {
+						let elapsed: Duration = started_at.elapsed();
+
+						// victims are already chosen, so slow inference only moves next evictions to fallback
+						if elapsed > budget {
+							self.slow_count += 1;
+							self.retry_at = now + self.cooldown;
+
+							warn!("model took {} microseconds over budget of {}, falling back for {} seconds\n", elapsed.as_micros(), budget.as_micros(), self.cooldown);
+						}
+					}
+
+					return Ok(victims);
+				},
+				Err(error) => {
+					self.failure_count += 1;
+					self.retry_at = now + self.cooldown;
+
+					warn!("{} from model, falling back for {} seconds\n", error, self.cooldown);
+				}
 			}
+		}
+
+		self.fallback_count += 1;
+
+		self.fallback.select_victims(entries, count)
+	}
 
-			i += 1;
+	// falling back selects under lock, as policy chosen for fallback is cheap
+	fn sample_candidates(self: &mut Self, entries: &Entries) -> Result<Option<Candidates>> {
+		if unix_epoch()? < self.retry_at {
+			return Ok(None);
 		}
 
-		Ok(keys[minimum_index].clone())
+		self.primary.sample_candidates(entries)
+	}
+
+	fn rank_candidates(self: &mut Self, keys: Vec<String>, scores: &[f32], count: usize) -> Vec<String> {
+		self.primary.rank_candidates(keys, scores, count)
+	}
+
+	fn on_insert(self: &mut Self, key: &str, entry: &Entry) {
+		self.primary.on_insert(key, entry);
+		self.fallback.on_insert(key, entry);
+	}
+
+	fn on_access(self: &mut Self, key: &str, entry: &Entry) {
+		self.primary.on_access(key, entry);
+		self.fallback.on_access(key, entry);
+	}
+
+	fn on_remove(self: &mut Self, key: &str, entry: &Entry, is_evicted: bool) {
+		self.primary.on_remove(key, entry, is_evicted);
+		self.fallback.on_remove(key, entry, is_evicted);
+	}
+
+	// falling back admits everything, as policy chosen for fallback has no scores to compare
+	fn admit(self: &mut Self, candidate: &Entry, victim: &Entry) -> Result<bool> {
+		if unix_epoch()? < self.retry_at {
+			return Ok(true);
+		}
+
+		self.primary.admit(candidate, victim)
+	}
+
+	fn reload(self: &mut Self, network: Arc<Mutex<Box<dyn Network + Send>>>) -> Result<()> {
+		self.primary.reload(network)?;
+
+		// freshly loaded model deserves immediate retry
+		self.retry_at = 0;
+
+		Ok(())
+	}
+
+	fn replace_network(self: &mut Self, network: Perceptron) -> Result<()> {
+		self.primary.replace_network(network)?;
+		self.retry_at = 0;
+
+		Ok(())
+	}
+
+	fn report(self: &Self, report: &mut String) {
+		let _ = write!(report, "fallback_active:{}\nfallback_failures:{}\nfallback_slow_inferences:{}\nfallback_evictions:{}\n", (unix_epoch().unwrap_or(0) < self.retry_at) as u8, self.failure_count, self.slow_count, self.fallback_count);
+
+		self.primary.report(report);
 	}
 }
 
 pub struct LeastRecentlyUsed {}
 
 impl Evictor for LeastRecentlyUsed {
-	fn select_victim(self: &mut Self, entries: &HashMap<String, Entry>) -> Result<String> {
+	fn select_victim(self: &mut Self, entries: &Entries) -> Result<String> {
+		if let Some(key) = entries.least_recently_used() {
+			Ok(key.clone())
+		} else {
+			Err(Box::from("entries length must be greater than 0"))
+		}
+	}
+}
+
+pub struct LeastFrequentlyUsed {}
+
+impl Evictor for LeastFrequentlyUsed {
+	fn select_victim(self: &mut Self, entries: &Entries) -> Result<String> {
+		if let Some(key) = entries.least_frequently_used() {
+			Ok(key.clone())
+		} else {
+			Err(Box::from("entries length must be greater than 0"))
+		}
+	}
+}
+
+// T1 and T2 hold cached keys seen once and more than once, B1 and B2 remember keys evicted from them
+pub struct AdaptiveReplacementCache {
+	recent: List,
+	frequent: List,
+	recent_ghost: List,
+	frequent_ghost: List,
+	target: usize,
+	capacity: usize
+}
+
+impl AdaptiveReplacementCache {
+	pub fn new(capacity: usize) -> Self {
+		AdaptiveReplacementCache {
+			recent: List::new(),
+			frequent: List::new(),
+			recent_ghost: List::new(),
+			frequent_ghost: List::new(),
+			target: 0,
+			capacity: capacity
+		}
+	}
+}
+
+impl Evictor for AdaptiveReplacementCache {
+	fn select_victim(self: &mut Self, entries: &Entries) -> Result<String> {
 		if entries.len() == 0 {
 			return Err(Box::from("entries length must be greater than 0"));
 		}
 
-		let mut minimum_accessed_at: u64 = u64::MAX;
-		let mut minimum_key: &String = &String::new();
+		let victim: Option<&String> = if self.recent.len() != 0 && (self.recent.len() > self.target || self.frequent.len() == 0) {
+			self.recent.back()
+		} else {
+			self.frequent.back()
+		};
+
+		if let Some(victim) = victim {
+			Ok(victim.clone())
+		} else {
+			Err(Box::from("adaptive replacement cache must track entries"))
+		}
+	}
+
+	fn on_insert(self: &mut Self, key: &str, _entry: &Entry) {
+		if self.recent_ghost.remove(key) {
+			self.target = (self.target + (self.frequent_ghost.len() / (self.recent_ghost.len() + 1)).max(1)).min(self.capacity);
+			self.frequent.push_front(key);
+		} else if self.frequent_ghost.remove(key) {
+			self.target = self.target.saturating_sub((self.recent_ghost.len() / (self.frequent_ghost.len() + 1)).max(1));
+			self.frequent.push_front(key);
+		} else {
+			self.recent.push_front(key);
+		}
+
+		while self.recent.len() + self.recent_ghost.len() > self.capacity && self.recent_ghost.pop_back().is_some() {}
+
+		while self.recent.len() + self.frequent.len() + self.recent_ghost.len() + self.frequent_ghost.len() > self.capacity * 2 && self.frequent_ghost.pop_back().is_some() {}
+	}
+
+	fn on_access(self: &mut Self, key: &str, _entry: &Entry) {
+		self.recent.remove(key);
+		self.frequent.push_front(key);
+	}
 
-		for entry in entries {
-			if entry.1.accessed_at < minimum_accessed_at {
-				minimum_accessed_at = entry.1.accessed_at;
-				minimum_key = entry.0;
+	fn on_remove(self: &mut Self, key: &str, _entry: &Entry, is_evicted: bool) {
+		if self.recent.remove(key) {
+			if is_evicted {
+				self.recent_ghost.push_front(key);
+			}
+		} else if self.frequent.remove(key) {
+			if is_evicted {
+				self.frequent_ghost.push_front(key);
 			}
 		}
+	}
+}
+
+// new keys enter small queue, keys hit more than once there or remembered by ghost queue go to main queue
+pub struct S3FirstInFirstOut {
+	small: List,
+	main: List,
+	ghost: List,
+	frequencies: HashMap<String, u8>,
+	small_capacity: usize,
+	ghost_capacity: usize
+}
+
+impl S3FirstInFirstOut {
+	pub fn new(capacity: usize) -> Self {
+		let small_capacity: usize = (capacity / 10).max(1);
 
-		Ok(minimum_key.clone())
+		S3FirstInFirstOut {
+			small: List::new(),
+			main: List::new(),
+			ghost: List::new(),
+			frequencies: HashMap::with_capacity(capacity),
+			small_capacity: small_capacity,
+			ghost_capacity: capacity.saturating_sub(small_capacity).max(1)
+		}
 	}
 }
 
-pub struct LeastFrequentlyUsed {}
+impl Evictor for S3FirstInFirstOut {
+	fn select_victim(self: &mut Self, _entries: &Entries) -> Result<String> {
+		loop {
+			if self.small.len() >= self.small_capacity || self.main.len() == 0 {
+				if let Some(key) = self.small.back() {
+					let key: String = key.clone();
+
+					if self.frequencies.get(&key).copied().unwrap_or(0) <= 1 {
+						return Ok(key);
+					}
+
+					self.small.remove(&key);
+					self.main.push_front(&key);
+					self.frequencies.insert(key, 0);
+
+					continue;
+				}
+			}
+
+			if let Some(key) = self.main.back() {
+				let key: String = key.clone();
+
+				if let Some(frequency) = self.frequencies.get_mut(&key) {
+					if *frequency != 0 {
+						*frequency -= 1;
+						self.main.push_front(&key);
+
+						continue;
+					}
+				}
+
+				return Ok(key);
+			}
 
-impl Evictor for LeastFrequentlyUsed {
-	fn select_victim(self: &mut Self, entries: &HashMap<String, Entry>) -> Result<String> {
-		if entries.len() == 0 {
 			return Err(Box::from("entries length must be greater than 0"));
 		}
+	}
 
-		let mut minimum_access_count: u64 = u64::MAX;
-		let mut minimum_key: &String = &String::new();
+	fn on_insert(self: &mut Self, key: &str, _entry: &Entry) {
+		if self.ghost.remove(key) {
+			self.main.push_front(key);
+		} else {
+			self.small.push_front(key);
+		}
+
+		self.frequencies.insert(key.to_owned(), 0);
+	}
 
-		for entry in entries {
-			if entry.1.access_count < minimum_access_count {
-				minimum_access_count = entry.1.access_count;
-				minimum_key = entry.0;
+	fn on_access(self: &mut Self, key: &str, _entry: &Entry) {
+		if let Some(frequency) = self.frequencies.get_mut(key) {
+			*frequency = (*frequency + 1).min(3);
+		}
+	}
+
+	fn on_remove(self: &mut Self, key: &str, _entry: &Entry, is_evicted: bool) {
+		self.frequencies.remove(key);
+
+		if self.small.remove(key) {
+			if is_evicted {
+				self.ghost.push_front(key);
+
+				while self.ghost.len() > self.ghost_capacity && self.ghost.pop_back().is_some() {}
+			}
+		} else {
+			self.main.remove(key);
+		}
+	}
+}
+
+// keys sit on circular buffer, hand clears reference bits until it finds key not referenced since last pass
+pub struct Clock {
+	slots: Vec<Option<(String, bool)>>,
+	vacancies: Vec<usize>,
+	indices: HashMap<String, usize>,
+	hand: usize
+}
+
+impl Clock {
+	pub fn new(capacity: usize) -> Self {
+		Clock {
+			slots: Vec::with_capacity(capacity),
+			vacancies: Vec::new(),
+			indices: HashMap::with_capacity(capacity),
+			hand: 0
+		}
+	}
+}
+
+impl Evictor for Clock {
+	fn select_victim(self: &mut Self, _entries: &Entries) -> Result<String> {
+		if self.indices.len() == 0 {
+			return Err(Box::from("entries length must be greater than 0"));
+		}
+
+		loop {
+			if self.hand >= self.slots.len() {
+				self.hand = 0;
+			}
+
+			if let Some((key, is_referenced)) = &mut self.slots[self.hand] {
+				if !*is_referenced {
+					return Ok(key.clone());
+				}
+
+				*is_referenced = false;
+			}
+
+			self.hand += 1;
+		}
+	}
+
+	fn on_insert(self: &mut Self, key: &str, _entry: &Entry) {
+		let slot: Option<(String, bool)> = Some((key.to_owned(), false));
+		let index: usize = if let Some(index) = self.vacancies.pop() {
+			self.slots[index] = slot;
+
+			index
+		} else {
+			self.slots.push(slot);
+
+			self.slots.len() - 1
+		};
+
+		self.indices.insert(key.to_owned(), index);
+	}
+
+	fn on_access(self: &mut Self, key: &str, _entry: &Entry) {
+		if let Some(index) = self.indices.get(key) {
+			if let Some((_, is_referenced)) = &mut self.slots[*index] {
+				*is_referenced = true;
+			}
+		}
+	}
+
+	fn on_remove(self: &mut Self, key: &str, _entry: &Entry, _is_evicted: bool) {
+		if let Some(index) = self.indices.remove(key) {
+			self.slots[index] = None;
+			self.vacancies.push(index);
+		}
+	}
+}
+
+// priority is inflation plus frequency times cost per byte, inflation rises to each victim priority so stale entries age out
+pub struct GreedyDualSizeFrequency {
+	priorities: BTreeMap<(u64, u64), String>,
+	keys: HashMap<String, (u64, u64)>,
+	inflation: f64,
+	average_cost: f64,
+	sequence: u64
+}
+
+impl GreedyDualSizeFrequency {
+	pub fn new() -> Self {
+		GreedyDualSizeFrequency {
+			priorities: BTreeMap::new(),
+			keys: HashMap::new(),
+			inflation: 0.0,
+			average_cost: 1.0,
+			sequence: 0
+		}
+	}
+
+	fn prioritize(self: &mut Self, key: &str, entry: &Entry) {
+		if let Some(priority) = self.keys.remove(key) {
+			self.priorities.remove(&priority);
+		}
+
+		// entries written without fetch are assumed to cost as much as average fetch
+		let cost: f64 = if entry.cost == 0 {
+			self.average_cost
+		} else {
+			entry.cost as f64
+		};
+		let priority: f64 = self.inflation + entry.access_count as f64 * cost / entry.size(key) as f64;
+
+		// bits of non-negative floats sort like floats, sequence breaks ties by age
+		let priority: (u64, u64) = (priority.to_bits(), self.sequence);
+
+		self.sequence += 1;
+		self.priorities.insert(priority, key.to_owned());
+		self.keys.insert(key.to_owned(), priority);
+	}
+}
+
+impl Evictor for GreedyDualSizeFrequency {
+	fn select_victim(self: &mut Self, _entries: &Entries) -> Result<String> {
+		if let Some((_, key)) = self.priorities.first_key_value() {
+			Ok(key.clone())
+		} else {
+			Err(Box::from("entries length must be greater than 0"))
+		}
+	}
+
+	fn on_insert(self: &mut Self, key: &str, entry: &Entry) {
+		if entry.cost != 0 {
+			self.average_cost = self.average_cost * 0.9 + entry.cost as f64 * 0.1;
+		}
+
+		self.prioritize(key, entry);
+	}
+
+	fn on_access(self: &mut Self, key: &str, entry: &Entry) {
+		self.prioritize(key, entry);
+	}
+
+	fn on_remove(self: &mut Self, key: &str, _entry: &Entry, is_evicted: bool) {
+		if let Some(priority) = self.keys.remove(key) {
+			self.priorities.remove(&priority);
+
+			if is_evicted {
+				self.inflation = f64::from_bits(priority.0);
 			}
 		}
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Expert {
+	LeastRecentlyUsed,
+	LeastFrequentlyUsed
+}
+
+// LeCaR, picks LRU or LFU victim by learned weights and punishes expert whose evicted key is requested again
+pub struct LearningCacheReplacement {
+	recency_weight: f64,
+	frequency_weight: f64,
+	learning_rate: f64,
+	discount: f64,
+	recency_history: List,
+	frequency_history: List,
+	evicted_at: HashMap<String, u64>,
+	history_capacity: usize,
+	time: u64,
+	victim: Option<(String, Expert)>,
+	random: Random
+}
+
+impl LearningCacheReplacement {
+	pub fn new(capacity: usize, learning_rate: f64, discount: Option<f64>) -> Self {
+		LearningCacheReplacement {
+			recency_weight: 0.5,
+			frequency_weight: 0.5,
+			learning_rate: learning_rate,
+			// regret of key evicted capacity requests ago is worth 0.005 of fresh one by default
+			discount: discount.unwrap_or(0.005f64.powf(1.0 / capacity as f64)),
+			recency_history: List::new(),
+			frequency_history: List::new(),
+			evicted_at: HashMap::with_capacity(capacity * 2),
+			history_capacity: capacity,
+			time: 0,
+			victim: None,
+			random: Random::new()
+		}
+	}
+
+	fn forget(self: &mut Self, expert: Expert) {
+		let history: &mut List = if expert == Expert::LeastRecentlyUsed {
+			&mut self.recency_history
+		} else {
+			&mut self.frequency_history
+		};
+
+		while history.len() > self.history_capacity {
+			if let Some(key) = history.pop_back() {
+				self.evicted_at.remove(&key);
+			}
+		}
+	}
+}
+
+impl Evictor for LearningCacheReplacement {
+	fn select_victim(self: &mut Self, entries: &Entries) -> Result<String> {
+		let (expert, victim): (Expert, Option<&String>) = if self.random.next_f64() < self.recency_weight {
+			(Expert::LeastRecentlyUsed, entries.least_recently_used())
+		} else {
+			(Expert::LeastFrequentlyUsed, entries.least_frequently_used())
+		};
+
+		if let Some(victim) = victim {
+			if ARGUMENT.is_verbose {
+				debug!("selected {:?} by {:?} with weights {:.4}, {:.4}\n", victim, expert, self.recency_weight, self.frequency_weight);
+			}
+
+			self.victim = Some((victim.clone(), expert));
+
+			Ok(victim.clone())
+		} else {
+			Err(Box::from("entries length must be greater than 0"))
+		}
+	}
+
+	fn on_insert(self: &mut Self, key: &str, _entry: &Entry) {
+		self.time += 1;
+
+		let (evicted_at, is_recency_regret): (u64, bool) = if let Some(evicted_at) = self.evicted_at.remove(key) {
+			(evicted_at, self.recency_history.remove(key))
+		} else {
+			return;
+		};
+
+		if !is_recency_regret {
+			self.frequency_history.remove(key);
+		}
+
+		let reward: f64 = (self.learning_rate * self.discount.powf((self.time - evicted_at) as f64)).exp();
+
+		// expert that wrongly evicted key loses weight to other expert
+		if is_recency_regret {
+			self.frequency_weight *= reward;
+		} else {
+			self.recency_weight *= reward;
+		}
+
+		let total_weight: f64 = self.recency_weight + self.frequency_weight;
+
+		self.recency_weight /= total_weight;
+		self.frequency_weight /= total_weight;
+	}
+
+	fn on_access(self: &mut Self, _key: &str, _entry: &Entry) {
+		self.time += 1;
+	}
+
+	fn on_remove(self: &mut Self, key: &str, _entry: &Entry, is_evicted: bool) {
+		let expert: Expert = match self.victim.take() {
+			Some((victim, expert)) if is_evicted && victim == key => expert,
+			_ => return
+		};
 
-		Ok(minimum_key.clone())
+		if expert == Expert::LeastRecentlyUsed {
+			self.recency_history.push_front(key);
+		} else {
+			self.frequency_history.push_front(key);
+		}
+
+		self.evicted_at.insert(key.to_owned(), self.time);
+		self.forget(expert);
 	}
 }
+
+// offline optimum, evicts key reused furthest in future of known trace, so hooks must arrive once per access in trace order
+pub struct Belady {
+	// position of next access to same key for every trace position, usize::MAX if never reused
+	next_uses: Vec<usize>,
+	position: usize,
+	// keyed by next use then position so never reused keys stay distinct
+	uses: BTreeMap<(usize, usize), String>,
+	keys: HashMap<String, (usize, usize)>
+}
+
+impl Belady {
+	pub fn new(trace: &[&str]) -> Self {
+		let mut next_uses: Vec<usize> = vec![usize::MAX; trace.len()];
+		let mut seen_at: HashMap<&str, usize> = HashMap::new();
+
+		for (position, key) in trace.iter().enumerate().rev() {
+			if let Some(next_use) = seen_at.insert(key, position) {
+				next_uses[position] = next_use;
+			}
+		}
+
+		Belady {
+			next_uses: next_uses,
+			position: 0,
+			uses: BTreeMap::new(),
+			keys: HashMap::new()
+		}
+	}
+
+	fn schedule(self: &mut Self, key: &str) {
+		if let Some(uses) = self.keys.remove(key) {
+			self.uses.remove(&uses);
+		}
+
+		let uses: (usize, usize) = (self.next_uses.get(self.position).copied().unwrap_or(usize::MAX), self.position);
+
+		self.position += 1;
+		self.uses.insert(uses, key.to_owned());
+		self.keys.insert(key.to_owned(), uses);
+	}
+}
+
+impl Evictor for Belady {
+	fn select_victim(self: &mut Self, _entries: &Entries) -> Result<String> {
+		if let Some((_, key)) = self.uses.last_key_value() {
+			Ok(key.clone())
+		} else {
+			Err(Box::from("entries length must be greater than 0"))
+		}
+	}
+
+	fn on_insert(self: &mut Self, key: &str, _entry: &Entry) {
+		self.schedule(key);
+	}
+
+	fn on_access(self: &mut Self, key: &str, _entry: &Entry) {
+		self.schedule(key);
+	}
+
+	fn on_remove(self: &mut Self, key: &str, _entry: &Entry, _is_evicted: bool) {
+		if let Some(uses) = self.keys.remove(key) {
+			self.uses.remove(&uses);
+		}
+	}
+}
\ No newline at end of file
@@ -0,0 +1,178 @@
+#[cfg(feature = "onnxruntime")]
+use ort::{
+	session::{
+		builder::GraphOptimizationLevel,
+		Session,
+		SessionOutputs
+	},
+	tensor::TensorElementType,
+	value::{Value, ValueType}
+};
+use std::{
+	borrow::Cow,
+	fs::{canonicalize, read},
+	path::PathBuf
+};
+use crate::{
+	common::{ARGUMENT, Result},
+	feature::{FEATURE_COUNT, FEATURE_SCHEMA_VERSION},
+	perceptron::Perceptron,
+	debug,
+	info
+};
+
+// scores rows of FEATURE_COUNT features, lower score is evicted first
+pub trait Network {
+	fn run(self: &mut Self, inputs: Vec<f32>, length: usize) -> Result<Vec<f32>>;
+
+	// weights online trainer can restart from, only pure rust evaluator exposes them
+	fn to_perceptron(self: &Self) -> Option<Perceptron> {
+		None
+	}
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Backend {
+	#[cfg(feature = "onnxruntime")]
+	OnnxRuntime,
+	Rust
+}
+
+// falls back to model embedded at compile time when no path is given
+pub fn read_model(path: Option<&str>) -> Result<Cow<'static, [u8]>> {
+	Ok(if let Some(path) = path {
+		Cow::Owned(read(path)?)
+	} else {
+		Cow::Borrowed(include_bytes!("../model.onnx"))
+	})
+}
+
+// clients may reload only configured model path or files under model directory, never arbitrary paths
+pub fn check_model_path(path: &str) -> Result<()> {
+	let is_allowed: bool = if let Ok(path) = canonicalize(path) {
+		ARGUMENT.model_path.as_ref()
+			.and_then(|model_path: &String| canonicalize(model_path).ok())
+			.is_some_and(|model_path: PathBuf| model_path == path) || ARGUMENT.model_directory.as_ref()
+			.and_then(|model_directory: &String| canonicalize(model_directory).ok())
+			.is_some_and(|model_directory: PathBuf| path.starts_with(model_directory))
+	} else {
+		false
+	};
+
+	if !is_allowed {
+		return Err(Box::from("model path must be model path or under model directory"));
+	}
+
+	Ok(())
+}
+
+pub fn load_network(path: Option<&str>, backend: Backend) -> Result<Box<dyn Network + Send>> {
+	if let Some(path) = path {
+		info!("loading model from {:?} with {:?} backend\n", path, backend);
+	}
+
+	let model: Cow<'static, [u8]> = read_model(path)?;
+
+	let mut network: Box<dyn Network + Send> = match backend {
+		#[cfg(feature = "onnxruntime")]
+		Backend::OnnxRuntime => Box::new(OnnxRuntime::new(&model)?),
+		Backend::Rust => Box::new(Perceptron::from_onnx(&model)?)
+	};
+
+	// dry run on single neutral entry so broken model is refused before serving any eviction
+	let output: Vec<f32> = network.run(vec![0.0; FEATURE_COUNT], 1)?;
+
+	if output.len() != 1 || !output[0].is_finite() {
+		return Err(Box::from("model must output one finite score per entry"));
+	}
+
+	Ok(network)
+}
+
+pub fn check_schema_version(version: Option<String>) -> Result<()> {
+	if let Some(version) = version {
+		let version: u32 = version.trim()
+			.parse::<u32>()
+			.map_err(|_| format!("model feature_schema_version must be integer, found {:?}", version))?;
+
+		if version != FEATURE_SCHEMA_VERSION {
+			return Err(Box::from(format!("model feature_schema_version must be {}, found {}", FEATURE_SCHEMA_VERSION, version)));
+		}
+	} else if ARGUMENT.is_verbose {
+		debug!("model does not declare feature_schema_version, assuming {}\n", FEATURE_SCHEMA_VERSION);
+	}
+
+	Ok(())
+}
+
+#[cfg(feature = "onnxruntime")]
+pub struct OnnxRuntime {
+	session: Session,
+	// discovered from model signature instead of assuming exporter naming
+	input: String
+}
+
+#[cfg(feature = "onnxruntime")]
+impl OnnxRuntime {
+	pub fn new(model: &[u8]) -> Result<Self> {
+		let session: Session = Session::builder()?
+			.with_optimization_level(GraphOptimizationLevel::Level3)?
+			.commit_from_memory(model)?;
+		let input: String = OnnxRuntime::inspect(&session)?;
+
+		Ok(OnnxRuntime {
+			session: session,
+			input: input
+		})
+	}
+
+	// checks signature against feature layout and returns input name
+	fn inspect(session: &Session) -> Result<String> {
+		if session.inputs.len() != 1 {
+			return Err(Box::from(format!("model must have 1 input, found {}", session.inputs.len())));
+		}
+
+		if session.outputs.len() != 1 {
+			return Err(Box::from(format!("model must have 1 output, found {}", session.outputs.len())));
+		}
+
+		let input: &str = &session.inputs[0].name;
+		let output: &str = &session.outputs[0].name;
+
+		match &session.inputs[0].input_type {
+			ValueType::Tensor { ty: TensorElementType::Float32, shape, .. } => {
+				if shape.len() != 2 {
+					return Err(Box::from(format!("model input {:?} must be rank 2 of [entries, features], found {}", input, shape)));
+				}
+
+				if shape[1] != FEATURE_COUNT as i64 {
+					return Err(Box::from(format!("model input {:?} must have {} features, found {}", input, FEATURE_COUNT, shape[1])));
+				}
+			},
+			input_type => return Err(Box::from(format!("model input {:?} must be float32 tensor, found {}", input, input_type)))
+		}
+
+		match &session.outputs[0].output_type {
+			ValueType::Tensor { ty: TensorElementType::Float32, shape, .. } => {
+				// either one score column or flat scores per entry
+				if !(shape.len() == 2 && shape[1] == 1 || shape.len() == 1) {
+					return Err(Box::from(format!("model output {:?} must be [entries, 1] or [entries], found {}", output, shape)));
+				}
+			},
+			output_type => return Err(Box::from(format!("model output {:?} must be float32 tensor, found {}", output, output_type)))
+		}
+
+		check_schema_version(session.metadata()?.custom("feature_schema_version")?)?;
+
+		Ok(input.to_owned())
+	}
+}
+
+#[cfg(feature = "onnxruntime")]
+impl Network for OnnxRuntime {
+	fn run(self: &mut Self, inputs: Vec<f32>, length: usize) -> Result<Vec<f32>> {
+		let output: SessionOutputs = self.session.run(vec![(self.input.as_str(), Value::from_array(([length, FEATURE_COUNT], inputs))?)])?;
+
+		Ok(output[0].try_extract_tensor::<f32>()?.1.to_vec())
+	}
+}
\ No newline at end of file
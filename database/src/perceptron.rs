@@ -0,0 +1,548 @@
+use std::{
+	collections::HashMap,
+	mem::swap
+};
+use crate::{
+	common::Result,
+	feature::FEATURE_COUNT,
+	network::{Network, check_schema_version}
+};
+
+// protobuf wire values, groups are not used by onnx
+enum Field<'a> {
+	Varint(u64),
+	Fixed64,
+	Bytes(&'a [u8]),
+	Fixed32([u8; 4])
+}
+
+struct Reader<'a> {
+	bytes: &'a [u8],
+	position: usize
+}
+
+impl<'a> Reader<'a> {
+	fn new(bytes: &'a [u8]) -> Self {
+		Reader {
+			bytes: bytes,
+			position: 0
+		}
+	}
+
+	fn read_varint(self: &mut Self) -> Result<u64> {
+		let mut value: u64 = 0;
+
+		for shift in (0..64).step_by(7) {
+			let byte: u8 = *self.bytes.get(self.position)
+				.ok_or("model varint must not be truncated")?;
+
+			self.position += 1;
+			value |= ((byte & 0x7f) as u64) << shift;
+
+			if byte & 0x80 == 0 {
+				return Ok(value);
+			}
+		}
+
+		Err(Box::from("model varint must be at most 10 bytes"))
+	}
+
+	fn read_bytes(self: &mut Self, length: usize) -> Result<&'a [u8]> {
+		let bytes: &'a [u8] = self.bytes.get(self.position..self.position + length)
+			.ok_or("model field must not be truncated")?;
+
+		self.position += length;
+
+		Ok(bytes)
+	}
+
+	fn next(self: &mut Self) -> Result<Option<(u64, Field<'a>)>> {
+		if self.position == self.bytes.len() {
+			return Ok(None);
+		}
+
+		let key: u64 = self.read_varint()?;
+
+		Ok(Some((key >> 3, match key & 7 {
+			0 => Field::Varint(self.read_varint()?),
+			1 => {
+				self.read_bytes(8)?;
+
+				Field::Fixed64
+			},
+			2 => {
+				let length: usize = self.read_varint()? as usize;
+
+				Field::Bytes(self.read_bytes(length)?)
+			},
+			5 => {
+				let bytes: &[u8] = self.read_bytes(4)?;
+
+				Field::Fixed32([bytes[0], bytes[1], bytes[2], bytes[3]])
+			},
+			wire_type => return Err(Box::from(format!("model wire type must be 0, 1, 2 or 5, found {}", wire_type)))
+		})))
+	}
+}
+
+fn read_string(bytes: &[u8]) -> Result<String> {
+	Ok(String::from_utf8(bytes.to_vec())?)
+}
+
+struct Tensor {
+	dimensions: Vec<usize>,
+	values: Vec<f32>
+}
+
+// TensorProto, only float32 data is accepted
+fn read_tensor(bytes: &[u8]) -> Result<(String, Tensor)> {
+	let mut reader: Reader = Reader::new(bytes);
+	let mut name: String = String::new();
+	let mut dimensions: Vec<usize> = Vec::new();
+	let mut values: Vec<f32> = Vec::new();
+	let mut data_type: u64 = 0;
+
+	while let Some((number, field)) = reader.next()? {
+		match (number, field) {
+			(1, Field::Varint(dimension)) => dimensions.push(dimension as usize),
+			(1, Field::Bytes(bytes)) => {
+				let mut reader: Reader = Reader::new(bytes);
+
+				while reader.position != bytes.len() {
+					dimensions.push(reader.read_varint()? as usize);
+				}
+			},
+			(2, Field::Varint(value)) => data_type = value,
+			(4, Field::Fixed32(bytes)) => values.push(f32::from_le_bytes(bytes)),
+			(4, Field::Bytes(bytes)) | (9, Field::Bytes(bytes)) => values.extend(bytes.chunks_exact(4)
+				.map(|bytes: &[u8]| f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))),
+			(8, Field::Bytes(bytes)) => name = read_string(bytes)?,
+			_ => {}
+		}
+	}
+
+	if data_type != 1 {
+		return Err(Box::from(format!("model initializer {:?} must be float32", name)));
+	}
+
+	if values.len() != dimensions.iter().product::<usize>() {
+		return Err(Box::from(format!("model initializer {:?} must hold {:?} values, found {}", name, dimensions, values.len())));
+	}
+
+	Ok((name, Tensor {
+		dimensions: dimensions,
+		values: values
+	}))
+}
+
+struct Node {
+	operation: String,
+	inputs: Vec<String>,
+	output: String,
+	alpha: Option<f32>
+}
+
+// NodeProto, alpha is only attribute used by supported operations
+fn read_node(bytes: &[u8]) -> Result<Node> {
+	let mut reader: Reader = Reader::new(bytes);
+	let mut node: Node = Node {
+		operation: String::new(),
+		inputs: Vec::new(),
+		output: String::new(),
+		alpha: None
+	};
+
+	while let Some((number, field)) = reader.next()? {
+		match (number, field) {
+			(1, Field::Bytes(bytes)) => node.inputs.push(read_string(bytes)?),
+			(2, Field::Bytes(bytes)) => node.output = read_string(bytes)?,
+			(4, Field::Bytes(bytes)) => node.operation = read_string(bytes)?,
+			(5, Field::Bytes(bytes)) => {
+				let mut reader: Reader = Reader::new(bytes);
+				let mut name: String = String::new();
+				let mut value: Option<f32> = None;
+
+				while let Some((number, field)) = reader.next()? {
+					match (number, field) {
+						(1, Field::Bytes(bytes)) => name = read_string(bytes)?,
+						(2, Field::Fixed32(bytes)) => value = Some(f32::from_le_bytes(bytes)),
+						_ => {}
+					}
+				}
+
+				if name == "alpha" {
+					node.alpha = value;
+				}
+			},
+			_ => {}
+		}
+	}
+
+	Ok(node)
+}
+
+// dense layer, weights are row major [inputs, outputs] as exported MatMul keeps them
+#[derive(Clone)]
+pub struct Layer {
+	pub weights: Vec<f32>,
+	pub bias: Vec<f32>,
+	pub output_count: usize,
+	// leaky relu slope, 0 for relu and none for linear output
+	pub alpha: Option<f32>
+}
+
+impl Layer {
+	pub fn forward(self: &Self, input: &[f32], output: &mut Vec<f32>) {
+		self.transform(input, output);
+		self.activate(output);
+	}
+
+	// dense part before activation, trainer keeps it to derive activation slope
+	pub fn transform(self: &Self, input: &[f32], output: &mut Vec<f32>) {
+		output.clear();
+		output.extend_from_slice(&self.bias);
+
+		for (i, x) in input.iter().enumerate() {
+			let weights: &[f32] = &self.weights[i * self.output_count..(i + 1) * self.output_count];
+
+			for (y, weight) in output.iter_mut().zip(weights) {
+				*y += x * weight;
+			}
+		}
+	}
+
+	pub fn activate(self: &Self, output: &mut [f32]) {
+		if let Some(alpha) = self.alpha {
+			for y in output.iter_mut() {
+				if *y < 0.0 {
+					*y *= alpha;
+				}
+			}
+		}
+	}
+}
+
+// dependency free evaluator for MatMul, Add and LeakyRelu chains exported from keras dense layers
+#[derive(Clone)]
+pub struct Perceptron {
+	pub layers: Vec<Layer>
+}
+
+impl Perceptron {
+	pub fn from_onnx(model: &[u8]) -> Result<Self> {
+		let mut reader: Reader = Reader::new(model);
+		let mut graph: Option<&[u8]> = None;
+		let mut version: Option<String> = None;
+
+		while let Some((number, field)) = reader.next()? {
+			match (number, field) {
+				(7, Field::Bytes(bytes)) => graph = Some(bytes),
+				(14, Field::Bytes(bytes)) => {
+					let mut reader: Reader = Reader::new(bytes);
+					let mut key: String = String::new();
+					let mut value: String = String::new();
+
+					while let Some((number, field)) = reader.next()? {
+						match (number, field) {
+							(1, Field::Bytes(bytes)) => key = read_string(bytes)?,
+							(2, Field::Bytes(bytes)) => value = read_string(bytes)?,
+							_ => {}
+						}
+					}
+
+					if key == "feature_schema_version" {
+						version = Some(value);
+					}
+				},
+				_ => {}
+			}
+		}
+
+		check_schema_version(version)?;
+
+		let mut reader: Reader = Reader::new(graph.ok_or("model must have graph")?);
+		let mut nodes: Vec<Node> = Vec::new();
+		let mut initializers: HashMap<String, Tensor> = HashMap::new();
+		let mut inputs: Vec<String> = Vec::new();
+		let mut outputs: Vec<String> = Vec::new();
+
+		while let Some((number, field)) = reader.next()? {
+			match (number, field) {
+				(1, Field::Bytes(bytes)) => nodes.push(read_node(bytes)?),
+				(5, Field::Bytes(bytes)) => {
+					let (name, tensor): (String, Tensor) = read_tensor(bytes)?;
+
+					initializers.insert(name, tensor);
+				},
+				(11 | 12, Field::Bytes(bytes)) => {
+					let mut reader: Reader = Reader::new(bytes);
+
+					while let Some((field_number, field)) = reader.next()? {
+						if let (1, Field::Bytes(bytes)) = (field_number, field) {
+							if number == 11 {
+								inputs.push(read_string(bytes)?);
+							} else {
+								outputs.push(read_string(bytes)?);
+							}
+						}
+					}
+				},
+				_ => {}
+			}
+		}
+
+		// older exporters list initializers as graph inputs too
+		inputs.retain(|input: &String| !initializers.contains_key(input));
+
+		if inputs.len() != 1 || outputs.len() != 1 {
+			return Err(Box::from(format!("model must have 1 input and 1 output, found {} and {}", inputs.len(), outputs.len())));
+		}
+
+		let mut layers: Vec<Layer> = Vec::new();
+		let mut current: String = inputs.remove(0);
+		let mut width: usize = FEATURE_COUNT;
+
+		for node in nodes {
+			let mut tensor: Option<&Tensor> = None;
+
+			for input in &node.inputs {
+				if let Some(initializer) = initializers.get(input) {
+					tensor = Some(initializer);
+				} else if *input != current {
+					return Err(Box::from(format!("model node {} must only consume previous output for rust backend", node.operation)));
+				}
+			}
+
+			match (node.operation.as_str(), tensor) {
+				("MatMul", Some(tensor)) => {
+					if tensor.dimensions.len() != 2 || tensor.dimensions[0] != width {
+						return Err(Box::from(format!("model MatMul weights must be [{}, outputs], found {:?}", width, tensor.dimensions)));
+					}
+
+					width = tensor.dimensions[1];
+					layers.push(Layer {
+						weights: tensor.values.clone(),
+						bias: vec![0.0; width],
+						output_count: width,
+						alpha: None
+					});
+				},
+				("Add", Some(tensor)) => match layers.last_mut() {
+					Some(layer) if layer.alpha.is_none() && tensor.values.len() == width => {
+						for (bias, value) in layer.bias.iter_mut().zip(&tensor.values) {
+							*bias += value;
+						}
+					},
+					_ => return Err(Box::from(format!("model Add must follow MatMul with {} biases, found {:?}", width, tensor.dimensions)))
+				},
+				("LeakyRelu" | "Relu", None) => match layers.last_mut() {
+					Some(layer) if layer.alpha.is_none() => {
+						// onnx default slope of LeakyRelu
+						layer.alpha = Some(if node.operation == "Relu" {
+							0.0
+						} else {
+							node.alpha.unwrap_or(0.01)
+						});
+					},
+					_ => return Err(Box::from(format!("model {} must follow dense layer", node.operation)))
+				},
+				("Identity", None) => {},
+				(operation, _) => return Err(Box::from(format!("model node {} must be MatMul, Add, Relu, LeakyRelu or Identity for rust backend", operation)))
+			}
+
+			current = node.output;
+		}
+
+		if current != outputs[0] {
+			return Err(Box::from(format!("model output {:?} must be produced by last node", outputs[0])));
+		}
+
+		if layers.len() == 0 || width != 1 {
+			return Err(Box::from(format!("model must end with one score per entry, found {}", width)));
+		}
+
+		Ok(Perceptron {
+			layers: layers
+		})
+	}
+
+	pub fn score(self: &Self, features: &[f32], buffers: &mut (Vec<f32>, Vec<f32>)) -> f32 {
+		buffers.0.clear();
+		buffers.0.extend_from_slice(features);
+
+		for layer in &self.layers {
+			layer.forward(&buffers.0, &mut buffers.1);
+
+			swap(&mut buffers.0, &mut buffers.1);
+		}
+
+		buffers.0[0]
+	}
+}
+
+impl Network for Perceptron {
+	fn run(self: &mut Self, inputs: Vec<f32>, length: usize) -> Result<Vec<f32>> {
+		let mut buffers: (Vec<f32>, Vec<f32>) = (Vec::new(), Vec::new());
+
+		Ok(inputs.chunks_exact(FEATURE_COUNT)
+			.take(length)
+			.map(|features: &[f32]| self.score(features, &mut buffers))
+			.collect::<Vec<f32>>())
+	}
+
+	fn to_perceptron(self: &Self) -> Option<Perceptron> {
+		Some(self.clone())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::f32::consts::LN_2;
+	use crate::network::read_model;
+	use super::{Layer, Perceptron};
+
+	// features of feature.rs golden vectors scored by float64 evaluation of model.onnx initializers outside this parser
+	const EMBEDDED_SCORES: [([f32; 4], f32); 4] = [
+		([0.0, 0.0, 0.0, 0.0], 34.710336),
+		([1.0, LN_2, 4.1743875, 4.8598123], 8.902962),
+		([15.201805, LN_2, 2.5649493, 5.549076], -0.16967643),
+		([3.1780539, 1.3862944, 6.240276, 4.8598123], 6.408426)
+	];
+
+	fn push_varint(mut value: u64, bytes: &mut Vec<u8>) {
+		while value >= 0x80 {
+			bytes.push((value as u8 & 0x7f) | 0x80);
+			value >>= 7;
+		}
+
+		bytes.push(value as u8);
+	}
+
+	fn push_varint_field(number: u64, value: u64, bytes: &mut Vec<u8>) {
+		push_varint(number << 3, bytes);
+		push_varint(value, bytes);
+	}
+
+	fn push_bytes_field(number: u64, value: &[u8], bytes: &mut Vec<u8>) {
+		push_varint(number << 3 | 2, bytes);
+		push_varint(value.len() as u64, bytes);
+		bytes.extend_from_slice(value);
+	}
+
+	fn encode_tensor(name: &str, dimensions: &[u64], data_type: u64, values: &[f32]) -> Vec<u8> {
+		let mut bytes: Vec<u8> = Vec::new();
+
+		for dimension in dimensions {
+			push_varint_field(1, *dimension, &mut bytes);
+		}
+
+		push_varint_field(2, data_type, &mut bytes);
+		push_bytes_field(8, name.as_bytes(), &mut bytes);
+		push_bytes_field(9, &values.iter()
+			.flat_map(|value: &f32| value.to_le_bytes())
+			.collect::<Vec<u8>>(), &mut bytes);
+
+		bytes
+	}
+
+	fn encode_node(operation: &str, inputs: &[&str], output: &str, alpha: Option<f32>) -> Vec<u8> {
+		let mut bytes: Vec<u8> = Vec::new();
+
+		for input in inputs {
+			push_bytes_field(1, input.as_bytes(), &mut bytes);
+		}
+
+		push_bytes_field(2, output.as_bytes(), &mut bytes);
+		push_bytes_field(4, operation.as_bytes(), &mut bytes);
+
+		if let Some(alpha) = alpha {
+			let mut attribute: Vec<u8> = Vec::new();
+
+			push_bytes_field(1, b"alpha", &mut attribute);
+			push_varint(2 << 3 | 5, &mut attribute);
+			attribute.extend_from_slice(&alpha.to_le_bytes());
+			push_varint_field(20, 1, &mut attribute);
+			push_bytes_field(5, &attribute, &mut bytes);
+		}
+
+		bytes
+	}
+
+	// two dense layers reading graph input x and writing y, activation and weight type are swappable to build rejected models
+	fn encode_model(activation: &str, data_type: u64) -> Vec<u8> {
+		let mut graph: Vec<u8> = Vec::new();
+		let mut value_info: Vec<u8> = Vec::new();
+		let mut model: Vec<u8> = Vec::new();
+		let mut metadata: Vec<u8> = Vec::new();
+
+		push_bytes_field(1, &encode_node("MatMul", &["x", "w0"], "h0", None), &mut graph);
+		push_bytes_field(1, &encode_node("Add", &["h0", "b0"], "h1", None), &mut graph);
+		push_bytes_field(1, &encode_node(activation, &["h1"], "h2", Some(0.5)), &mut graph);
+		push_bytes_field(1, &encode_node("MatMul", &["h2", "w1"], "h3", None), &mut graph);
+		push_bytes_field(1, &encode_node("Add", &["h3", "b1"], "y", None), &mut graph);
+		push_bytes_field(5, &encode_tensor("w0", &[4, 2], data_type, &[1.0, -1.0, 2.0, -2.0, 3.0, -3.0, 4.0, -4.0]), &mut graph);
+		push_bytes_field(5, &encode_tensor("b0", &[2], 1, &[0.5, 0.5]), &mut graph);
+		push_bytes_field(5, &encode_tensor("w1", &[2, 1], 1, &[1.0, 2.0]), &mut graph);
+		push_bytes_field(5, &encode_tensor("b1", &[1], 1, &[0.25]), &mut graph);
+		push_bytes_field(1, b"x", &mut value_info);
+		push_bytes_field(11, &value_info, &mut graph);
+		value_info.clear();
+		push_bytes_field(1, b"y", &mut value_info);
+		push_bytes_field(12, &value_info, &mut graph);
+		push_bytes_field(7, &graph, &mut model);
+		push_bytes_field(1, b"feature_schema_version", &mut metadata);
+		push_bytes_field(2, b"1", &mut metadata);
+		push_bytes_field(14, &metadata, &mut model);
+
+		model
+	}
+
+	fn score(perceptron: &Perceptron, features: &[f32]) -> f32 {
+		perceptron.score(features, &mut (Vec::new(), Vec::new()))
+	}
+
+	#[test]
+	fn scores_embedded_model() {
+		let perceptron: Perceptron = Perceptron::from_onnx(&read_model(None).unwrap()).unwrap();
+
+		assert_eq!(perceptron.layers.iter()
+			.map(|layer: &Layer| (layer.output_count, layer.alpha))
+			.collect::<Vec<(usize, Option<f32>)>>(), vec![(64, Some(0.2)), (32, Some(0.2)), (1, None)]);
+
+		for (features, expected) in EMBEDDED_SCORES {
+			let actual: f32 = score(&perceptron, &features);
+
+			assert!((actual - expected).abs() <= 1e-4 * expected.abs().max(1.0), "score of {:?} must be {}, found {}", features, expected, actual);
+		}
+	}
+
+	#[test]
+	fn scores_encoded_model() {
+		let leaky: Perceptron = Perceptron::from_onnx(&encode_model("LeakyRelu", 1)).unwrap();
+		let relu: Perceptron = Perceptron::from_onnx(&encode_model("Relu", 1)).unwrap();
+
+		// hidden layer is [10.5, -9.5] before activation
+		assert_eq!(score(&leaky, &[1.0, 1.0, 1.0, 1.0]), 10.5 - 9.5 + 0.25);
+		assert_eq!(score(&relu, &[1.0, 1.0, 1.0, 1.0]), 10.5 + 0.25);
+		// hidden layer is [-2.5, 3.5] before activation
+		assert_eq!(score(&leaky, &[0.0, 0.0, 0.0, -0.75]), -1.25 + 7.0 + 0.25);
+	}
+
+	#[test]
+	fn rejects_unsupported_models() {
+		let error: String = Perceptron::from_onnx(&encode_model("Sigmoid", 1)).err().unwrap().to_string();
+
+		assert!(error.contains("Sigmoid must be MatMul"), "unexpected error {:?}", error);
+
+		// int64 and float16 weights
+		for data_type in [7, 10] {
+			let error: String = Perceptron::from_onnx(&encode_model("LeakyRelu", data_type)).err().unwrap().to_string();
+
+			assert!(error.contains("\"w0\" must be float32"), "unexpected error {:?}", error);
+		}
+
+		let model: Vec<u8> = encode_model("LeakyRelu", 1);
+
+		assert!(Perceptron::from_onnx(&model[..model.len() - 1]).is_err());
+	}
+}
\ No newline at end of file
@@ -19,14 +19,26 @@ use crate::{
 
 	-- request --
 	NOP
-	SET   <length:u8> <key:String> <length:u32> <value:String>
-	DEL   <length:u8> <key:String>
-	GET   <length:u8> <key:String>
+	SET     <length:u8> <key:String> <length:u32> <value:String> <ttl:u32>
+	DEL     <length:u8> <key:String>
+	GET     <length:u8> <key:String>
+	EXPIRE  <length:u8> <key:String> <ttl:u32>
+	TTL     <length:u8> <key:String>
+	PERSIST <length:u8> <key:String>
+	RELOAD  <length:u32> <path:String>
+	STATS
+
+	ttl is in seconds, 0 on SET means no expiry and it is only sent by clients since 0.0.2
 
 	-- responses --
 	OKAY
-	VALUE <length:u32> <value:String>
-	ERROR <length:u32> <message:String>
+	VALUE  <length:u32> <value:String>
+	NUMBER <value:u32>
+	ERROR  <length:u32> <message:String>
+
+	NUMBER to TTL is remaining seconds, 0 if key never expires
+	RELOAD swaps DQN model with ONNX file at path on server, old model is kept on ERROR
+	VALUE to STATS is name:value lines of cache counters
 
 	-- termination --
 	QUIT
@@ -38,8 +50,14 @@ pub const OPERATION_NOP: &[u8; 1] = &[0b00000010];
 pub const OPERATION_SET: &[u8; 1] = &[0b00000011];
 pub const OPERATION_DEL: &[u8; 1] = &[0b00000100];
 pub const OPERATION_GET: &[u8; 1] = &[0b00000101];
+pub const OPERATION_EXPIRE: &[u8; 1] = &[0b00000110];
+pub const OPERATION_TTL: &[u8; 1] = &[0b00000111];
+pub const OPERATION_PERSIST: &[u8; 1] = &[0b00001000];
+pub const OPERATION_RELOAD: &[u8; 1] = &[0b00001001];
+pub const OPERATION_STATS: &[u8; 1] = &[0b00001010];
 pub const OPERATION_OK: &[u8; 1] = &[0b10000010];
 pub const OPERATION_VALUE: &[u8; 1] = &[0b10000011];
+pub const OPERATION_NUMBER: &[u8; 1] = &[0b10000101];
 pub const OPERATION_ERROR: &[u8; 1] = &[0b10000100];
 pub const OPERATION_QUIT: &[u8; 1] = &[0b11111111];
 
@@ -63,6 +81,43 @@ pub fn read_string<const N: usize>(stream: &mut TcpStream, byte_or_double_word:
 	Ok(String::from_utf8(buffer)?)
 }
 
+pub fn read_u32(stream: &mut TcpStream, double_word: &mut [u8; 4]) -> Result<u32> {
+	stream.read_exact(double_word)?;
+
+	Ok((double_word[0] as u32) << 24 | (double_word[1] as u32) << 16 | (double_word[2] as u32) << 8 | double_word[3] as u32)
+}
+
+pub fn send_value(stream: &mut TcpStream, double_word: &mut [u8; 4], value: &str) -> Result<()> {
+	let value_length: usize = value.len();
+
+	double_word[0] = (value_length >> 24) as u8;
+	double_word[1] = (value_length >> 16) as u8;
+	double_word[2] = (value_length >> 8) as u8;
+	double_word[3] = value_length as u8;
+
+	stream.write_vectored(&[
+		IoSlice::new(OPERATION_VALUE),
+		IoSlice::new(double_word),
+		IoSlice::new(value.as_bytes())
+	])?;
+
+	Ok(())
+}
+
+pub fn send_number(stream: &mut TcpStream, double_word: &mut [u8; 4], number: u32) -> Result<()> {
+	double_word[0] = (number >> 24) as u8;
+	double_word[1] = (number >> 16) as u8;
+	double_word[2] = (number >> 8) as u8;
+	double_word[3] = number as u8;
+
+	stream.write_vectored(&[
+		IoSlice::new(OPERATION_NUMBER),
+		IoSlice::new(double_word)
+	])?;
+
+	Ok(())
+}
+
 pub fn send_error(stream: &mut TcpStream, double_word: &mut [u8; 4], message: String) -> Result<()> {
 	let message_length: usize = message.len();
 
@@ -82,6 +137,7 @@ pub fn send_error(stream: &mut TcpStream, double_word: &mut [u8; 4], message: St
 	Ok(())
 }
 
+#[derive(Clone, Copy)]
 pub struct Version {
 	major: u8,
 	minor: u8,
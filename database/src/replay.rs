@@ -0,0 +1,191 @@
+use std::{
+	fs::read_to_string,
+	iter::once,
+	sync::{atomic::AtomicU64, Arc}
+};
+use crate::{
+	cache::{Entry, Model},
+	common::{ARGUMENT, AccessClock, Result},
+	model::{Belady, DeepQNetwork},
+	shadow::Shadow,
+	info
+};
+
+// policies measured against belady, which only replay can build
+const REPLAYED_MODELS: [Model; 3] = [Model::DeepQNetwork, Model::LeastRecentlyUsed, Model::LeastFrequentlyUsed];
+
+struct Access {
+	key: String,
+	// c_time as trained on, so features see same recency gaps as train.py
+	time: u64,
+	size: usize,
+	is_write: bool
+}
+
+// thesios csv as loaded by load_datas of model/train.py, rows with empty field or zero size are dropped
+fn read_trace(path: &str) -> Result<Vec<Access>> {
+	let trace: String = read_to_string(path)?;
+	let mut lines = trace.lines();
+	let header: Vec<&str> = lines.next()
+		.ok_or("trace must have header")?
+		.split(',')
+		.map(|column: &str| column.trim())
+		.collect::<Vec<&str>>();
+	let find_column = |name: &str| -> Result<usize> {
+		header.iter()
+			.position(|column: &&str| *column == name)
+			.ok_or_else(|| Box::from(format!("trace must have {} column", name)))
+	};
+	let key_column: usize = find_column("filename")?;
+	let time_column: usize = find_column("c_time")?;
+	let operation_column: usize = find_column("op_type")?;
+	let size_column: usize = find_column("request_io_size_bytes")?;
+	let mut accesses: Vec<Access> = Vec::new();
+
+	for line in lines {
+		let fields: Vec<&str> = line.split(',')
+			.map(|field: &str| field.trim())
+			.collect::<Vec<&str>>();
+
+		if fields.len() != header.len() || fields.iter().any(|field: &&str| field.is_empty()) {
+			continue;
+		}
+
+		let size: usize = fields[size_column].parse::<f64>()? as usize;
+
+		if size == 0 {
+			continue;
+		}
+
+		accesses.push(Access {
+			key: fields[key_column].to_owned(),
+			time: fields[time_column].parse::<f64>()? as u64,
+			size: size,
+			is_write: fields[operation_column] == "WRITE"
+		});
+	}
+
+	Ok(accesses)
+}
+
+fn get_hit_rate(shadow: &Shadow) -> f64 {
+	let (hit_count, miss_count): (u64, u64) = shadow.counts();
+
+	if hit_count + miss_count == 0 {
+		0.0
+	} else {
+		hit_count as f64 / (hit_count + miss_count) as f64
+	}
+}
+
+// every access fetches on miss, write hit resizes entry and capacity counts entries only, as in Environment.iterate of model/train.py
+pub fn replay(path: &str) -> Result<()> {
+	let trace: Vec<Access> = read_trace(path)?;
+
+	if trace.len() == 0 {
+		return Err(Box::from("trace must have accesses"));
+	}
+
+	info!("replaying {} accesses from {:?} with capacity of {}\n", trace.len(), path, ARGUMENT.capacity);
+
+	let keys: Vec<&str> = trace.iter()
+		.map(|access: &Access| access.key.as_str())
+		.collect::<Vec<&str>>();
+	let mut oracle: Shadow = Shadow::with_evictor(Model::Belady, ARGUMENT.capacity, None, Box::new(Belady::new(&keys)));
+	// dqn reads time through clock, so features see trace time instead of wall clock
+	let clock: AccessClock = AccessClock::Manual(Arc::new(AtomicU64::new(0)));
+	let mut shadows: Vec<Shadow> = REPLAYED_MODELS.iter()
+		.map(|model: &Model| -> Result<Shadow> {
+			Ok(if *model == Model::DeepQNetwork {
+				Shadow::with_evictor(*model, ARGUMENT.capacity, None, Box::new(DeepQNetwork::new(ARGUMENT.model_path.as_deref(), ARGUMENT.backend, ARGUMENT.capacity, ARGUMENT.eviction_samples, ARGUMENT.eviction_pool, clock.clone())?))
+			} else {
+				Shadow::new(*model, ARGUMENT.capacity, None)?
+			})
+		})
+		.collect::<Result<Vec<Shadow>>>()?;
+
+	for access in &trace {
+		let accessed_at: u64 = access.time;
+
+		clock.set(accessed_at);
+
+		let entry: Entry = Entry {
+			value: String::new(),
+			length: access.size,
+			accessed_at: accessed_at,
+			access_count: 1,
+			expires_at: None,
+			cost: 0
+		};
+
+		// missed key is already fetched at this size, so resizing after lookup only changes hits
+		for shadow in once(&mut oracle).chain(&mut shadows) {
+			shadow.get(&access.key, accessed_at, Some(&entry))?;
+
+			if access.is_write {
+				shadow.resize(&access.key, access.size);
+			}
+		}
+	}
+
+	let optimal_hit_rate: f64 = get_hit_rate(&oracle);
+
+	info!("{} hit rate {:.4}\n", oracle.name(), optimal_hit_rate);
+
+	for shadow in &shadows {
+		let hit_rate: f64 = get_hit_rate(shadow);
+
+		info!("{} hit rate {:.4} ({:.2}% of optimal)\n", shadow.name(), hit_rate, if optimal_hit_rate == 0.0 {
+			100.0
+		} else {
+			hit_rate / optimal_hit_rate * 100.0
+		});
+	}
+
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::{
+		cache::{Entry, Model},
+		model::Belady,
+		shadow::Shadow
+	};
+
+	// (trace, capacity, hits) where hits are worked out by hand evicting key used farthest ahead
+	const OPTIMAL_HITS: [(&str, usize, u64); 3] = [
+		// a b c miss, d evicts c, e evicts d, c and d miss at end once a and b are done
+		("abcdabeabcde", 3, 5),
+		("abcabc", 2, 2),
+		("aabba", 1, 2)
+	];
+
+	#[test]
+	fn matches_hand_computed_optimum() {
+		for (trace, capacity, hits) in OPTIMAL_HITS {
+			let keys: Vec<String> = trace.chars()
+				.map(|key: char| key.to_string())
+				.collect::<Vec<String>>();
+			let keys: Vec<&str> = keys.iter()
+				.map(String::as_str)
+				.collect::<Vec<&str>>();
+			let mut oracle: Shadow = Shadow::with_evictor(Model::Belady, capacity, None, Box::new(Belady::new(&keys)));
+
+			for (time, key) in keys.iter().enumerate() {
+				let entry: Entry = Entry {
+					value: String::new(),
+					length: 1,
+					accessed_at: time as u64,
+					access_count: 1,
+					expires_at: None,
+					cost: 0
+				};
+
+				oracle.get(key, time as u64, Some(&entry)).unwrap();
+			}
+
+			assert_eq!(oracle.counts(), (hits, keys.len() as u64 - hits), "belady hits of {:?} at capacity {}", trace, capacity);
+		}
+	}
+}
\ No newline at end of file
@@ -0,0 +1,159 @@
+use std::{
+	fmt::Write,
+	sync::{Arc, Mutex}
+};
+use crate::{
+	cache::{Cache, Entry, Evictor, Model},
+	common::Result,
+	entries::Entries,
+	network::Network
+};
+
+// key only cache replaying real access stream under another model, so its hit rate can be compared live
+pub struct Shadow {
+	model: Model,
+	capacity: usize,
+	// ghosts are sized by length they were stored with, as if values were kept
+	size: usize,
+	maximum_size: Option<usize>,
+	entries: Entries,
+	evictor: Box<dyn Evictor + Send>,
+	hit_count: u64,
+	miss_count: u64
+}
+
+impl Shadow {
+	pub fn new(model: Model, capacity: usize, maximum_size: Option<usize>) -> Result<Self> {
+		Ok(Shadow::with_evictor(model, capacity, maximum_size, Cache::create_model(model, capacity)?))
+	}
+
+	// for evictors model alone cannot build, such as belady needing trace ahead
+	pub fn with_evictor(model: Model, capacity: usize, maximum_size: Option<usize>, evictor: Box<dyn Evictor + Send>) -> Self {
+		Shadow {
+			model: model,
+			capacity: capacity,
+			size: 0,
+			maximum_size: maximum_size,
+			entries: Entries::with_capacity(capacity),
+			evictor: evictor,
+			hit_count: 0,
+			miss_count: 0
+		}
+	}
+
+	pub fn model(self: &Self) -> Model {
+		self.model
+	}
+
+	pub fn name(self: &Self) -> String {
+		format!("{}_{}", self.model, self.capacity)
+	}
+
+	fn is_full(self: &Self, size: usize) -> bool {
+		self.entries.len() >= self.capacity || if let Some(maximum_size) = self.maximum_size {
+			self.size + size > maximum_size
+		} else {
+			false
+		}
+	}
+
+	// ghost keeps its entries, only later evictions follow new network
+	pub fn reload(self: &mut Self, network: Arc<Mutex<Box<dyn Network + Send>>>) -> Result<()> {
+		self.evictor.reload(network)
+	}
+
+	pub fn counts(self: &Self) -> (u64, u64) {
+		(self.hit_count, self.miss_count)
+	}
+
+	// entry is what real cache served, ghost takes it on miss as server would fetch it anyway
+	pub fn get(self: &mut Self, key: &str, now: u64, entry: Option<&Entry>) -> Result<()> {
+		if self.entries.get(key).is_some() {
+			self.hit_count += 1;
+			self.entries.touch(key, now);
+
+			if let Some(entry) = self.entries.get(key) {
+				self.evictor.on_access(key, entry);
+			}
+		} else {
+			self.miss_count += 1;
+
+			if let Some(entry) = entry {
+				self.set(key, entry, now)?;
+			}
+		}
+
+		Ok(())
+	}
+
+	pub fn set(self: &mut Self, key: &str, entry: &Entry, now: u64) -> Result<()> {
+		if let Some(ghost) = self.entries.get_mut(key) {
+			// real cache does not evict on update either, next insert restores budget
+			self.size = self.size - ghost.length + entry.length;
+			ghost.length = entry.length;
+
+			if entry.cost != 0 {
+				ghost.cost = entry.cost;
+			}
+
+			self.entries.touch(key, now);
+
+			if let Some(ghost) = self.entries.get(key) {
+				self.evictor.on_access(key, ghost);
+			}
+
+			return Ok(());
+		}
+
+		let size: usize = entry.size(key);
+
+		// real cache skips such value too, so it would miss again
+		if let Some(maximum_size) = self.maximum_size {
+			if size > maximum_size {
+				return Ok(());
+			}
+		}
+
+		while self.entries.len() != 0 && self.is_full(size) {
+			let victim_key: String = self.evictor.select_victim(&self.entries)?;
+
+			if let Some(ghost) = self.entries.remove(&victim_key) {
+				self.size -= ghost.size(&victim_key);
+				self.evictor.on_remove(&victim_key, &ghost, true);
+			} else {
+				return Err(Box::from(format!("shadow victim {:?} must be cached", victim_key)));
+			}
+		}
+
+		let mut ghost: Entry = entry.to_ghost();
+
+		ghost.accessed_at = now;
+		self.size += size;
+		self.entries.insert(key.to_owned(), ghost);
+
+		if let Some(ghost) = self.entries.get(key) {
+			self.evictor.on_insert(key, ghost);
+		}
+
+		Ok(())
+	}
+
+	// rewritten ghost takes new length without counting as another access
+	pub fn resize(self: &mut Self, key: &str, length: usize) {
+		if let Some(ghost) = self.entries.get_mut(key) {
+			self.size = self.size - ghost.length + length;
+			ghost.length = length;
+		}
+	}
+
+	pub fn remove(self: &mut Self, key: &str) {
+		if let Some(ghost) = self.entries.remove(key) {
+			self.size -= ghost.size(key);
+			self.evictor.on_remove(key, &ghost, false);
+		}
+	}
+
+	pub fn report(self: &Self, report: &mut String) {
+		let _ = write!(report, "shadow_{}_hits:{}\nshadow_{}_misses:{}\n", self.name(), self.hit_count, self.name(), self.miss_count);
+	}
+}
\ No newline at end of file
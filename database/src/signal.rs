@@ -0,0 +1,36 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use crate::common::Result;
+
+static IS_HUNG_UP: AtomicBool = AtomicBool::new(false);
+
+#[cfg(unix)]
+extern "C" fn handle_hangup(_signal: i32) {
+	// only async signal safe work is allowed here, watcher thread does actual reload
+	IS_HUNG_UP.store(true, Ordering::SeqCst);
+}
+
+#[cfg(unix)]
+pub fn listen_hangup() -> Result<()> {
+	extern "C" {
+		fn signal(signal: i32, handler: extern "C" fn(i32)) -> usize;
+	}
+
+	const SIGHUP: i32 = 1;
+	const SIG_ERR: usize = usize::MAX;
+
+	if unsafe { signal(SIGHUP, handle_hangup) } == SIG_ERR {
+		return Err(Box::from("hangup handler must be registered"));
+	}
+
+	Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn listen_hangup() -> Result<()> {
+	Ok(())
+}
+
+// clears flag so each hangup is handled once
+pub fn is_hung_up() -> bool {
+	IS_HUNG_UP.swap(false, Ordering::SeqCst)
+}
\ No newline at end of file
@@ -1,27 +1,83 @@
 use std::{
-	fs::{create_dir_all, exists, read, remove_file, write},
+	collections::HashMap,
+	fs::{DirEntry, create_dir_all, exists, read, read_dir, read_to_string, remove_file, write},
 	path::PathBuf
 };
 use crate::{
-	common::{ARGUMENT, Result},
-	debug
+	common::{ARGUMENT, Result, unix_epoch},
+	debug,
+	warn
 };
 
+pub const EXPIRY_FOLDER: &str = ".expiry";
+pub const EXPERIENCE_FOLDER: &str = ".experience";
+
+// keys are file names beside metadata folders, so hidden names and paths must never reach cache or storage
+pub fn check_key(key: &str) -> Result<()> {
+	if key.starts_with('.') || key.contains(['/', '\\']) {
+		return Err(Box::from("key must not start with '.' or contain path separator"));
+	}
+
+	Ok(())
+}
+
 pub struct Storage {
-	root: PathBuf
+	root: PathBuf,
+	expiry_root: PathBuf,
+	expirations: HashMap<String, u64>
 }
 
 impl Storage {
 	pub fn new(root: &str) -> Result<Storage> {
 		let root: PathBuf = PathBuf::from(root);
+		let expiry_root: PathBuf = root.join(EXPIRY_FOLDER);
+		let mut expirations: HashMap<String, u64> = HashMap::new();
+
+		create_dir_all(&expiry_root)?;
 
-		create_dir_all(&root)?;
+		for file in read_dir(&expiry_root)? {
+			let file: DirEntry = file?;
+
+			if let Ok(key) = file.file_name().into_string() {
+				// stray file must not stop server from starting
+				match read_to_string(file.path())?.trim().parse::<u64>() {
+					Ok(expires_at) => {
+						expirations.insert(key, expires_at);
+					},
+					Err(error) => {
+						warn!("skipped expiration of {:?} as {}\n", key, error);
+					}
+				}
+			}
+		}
+
+		if ARGUMENT.is_verbose {
+			debug!("loaded {} expirations from {:?}\n", expirations.len(), expiry_root.display());
+		}
 
 		Ok(Storage {
-			root: root
+			root: root,
+			expiry_root: expiry_root,
+			expirations: expirations
 		})
 	}
 
+	fn is_expired(self: &Self, key: &str) -> Result<bool> {
+		Ok(if let Some(expires_at) = self.expirations.get(key) {
+			*expires_at <= unix_epoch()?
+		} else {
+			false
+		})
+	}
+
+	pub fn contains(self: &Self, key: &str) -> Result<bool> {
+		Ok(exists(self.root.join(key))? && !self.is_expired(key)?)
+	}
+
+	pub fn expiration(self: &Self, key: &str) -> Option<u64> {
+		self.expirations.get(key).copied()
+	}
+
 	pub fn read(self: &Self, key: &str) -> Result<Option<String>> {
 		let file: PathBuf = self.root.join(key);
 
@@ -29,36 +85,86 @@ impl Storage {
 			debug!("read {:?} from {:?}\n", key, file.display());
 		}
 
-		Ok(if exists(&file)? {
+		Ok(if exists(&file)? && !self.is_expired(key)? {
 			Some(String::from_utf8(read(&file)?)?)
 		} else {
 			None
 		})
 	}
 
-	pub fn write(self: &Self, key: &str, value: String) -> Result<()> {
+	pub fn write(self: &mut Self, key: &str, value: String, expires_at: Option<u64>) -> Result<()> {
 		let file: PathBuf = self.root.join(key);
 
 		if ARGUMENT.is_verbose {
 			debug!("wrote {:?} to {:?}\n", key, file.display());
 		}
 
-		Ok(write(&file, value)?)
+		write(&file, value)?;
+
+		self.set_expiration(key, expires_at)
 	}
 
-	pub fn delete(self: &Self, key: &str) -> Result<bool> {
-		let file: PathBuf = self.root.join(key);
+	pub fn expire(self: &mut Self, key: &str, expires_at: Option<u64>) -> Result<bool> {
+		if !self.contains(key)? {
+			return Ok(false);
+		}
 
-		if exists(&file)? {
+		self.set_expiration(key, expires_at)?;
+
+		Ok(true)
+	}
+
+	fn set_expiration(self: &mut Self, key: &str, expires_at: Option<u64>) -> Result<()> {
+		let file: PathBuf = self.expiry_root.join(key);
+
+		if let Some(expires_at) = expires_at {
+			if ARGUMENT.is_verbose {
+				debug!("expiring {:?} at {}\n", key, expires_at);
+			}
+
+			write(&file, expires_at.to_string())?;
+			self.expirations.insert(key.to_owned(), expires_at);
+		} else if self.expirations.remove(key).is_some() {
+			if ARGUMENT.is_verbose {
+				debug!("persisted {:?}\n", key);
+			}
+
+			remove_file(&file)?;
+		}
+
+		Ok(())
+	}
+
+	pub fn delete(self: &mut Self, key: &str) -> Result<bool> {
+		let file: PathBuf = self.root.join(key);
+		let is_deleted: bool = if exists(&file)? {
 			if ARGUMENT.is_verbose {
 				debug!("deleted {:?} from {:?}\n", key, file.display());
 			}
 
 			remove_file(&file)?;
 
-			Ok(true)
+			!self.is_expired(key)?
 		} else {
-			Ok(false)
+			false
+		};
+
+		self.set_expiration(key, None)?;
+
+		Ok(is_deleted)
+	}
+
+	pub fn sweep(self: &mut Self) -> Result<Vec<String>> {
+		let now: u64 = unix_epoch()?;
+		let keys: Vec<String> = self.expirations.iter()
+			.filter(|(_, expires_at): &(&String, &u64)| **expires_at <= now)
+			.map(|(key, _): (&String, &u64)| key.clone())
+			.collect::<Vec<String>>();
+
+		for key in &keys {
+			self.delete(key)?;
 		}
+
+		Ok(keys)
 	}
 }
\ No newline at end of file
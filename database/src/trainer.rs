@@ -0,0 +1,376 @@
+use std::{
+	mem::replace,
+	sync::{
+		mpsc::{Receiver, RecvTimeoutError},
+		Arc,
+		Mutex,
+		MutexGuard,
+		PoisonError
+	},
+	time::{Duration, Instant}
+};
+use crate::{
+	cache::Cache,
+	common::{ARGUMENT, Random, Result},
+	feature::FEATURE_COUNT,
+	perceptron::{Layer, Perceptron},
+	debug,
+	error,
+	info,
+	warn
+};
+
+// hyperparameters of model/train.py, except replay buffer which is kept small for server memory
+const LEARNING_RATE: f32 = 0.001;
+const GAMMA: f32 = 0.95;
+const BATCH_SIZE: usize = 128;
+const TARGET_UPDATE_FREQUENCY: u64 = 16384;
+const REPLAY_BUFFER_SIZE: usize = 65536;
+// keras Adam defaults
+const BETA_1: f32 = 0.9;
+const BETA_2: f32 = 0.999;
+const EPSILON: f32 = 1e-7;
+// windows with fewer lookups are too noisy to judge published weights
+const MINIMUM_WINDOW: u64 = 1000;
+// published weights are reverted once window hit rate falls further than this below previous window
+const REGRESSION_TOLERANCE: f64 = 0.01;
+
+pub struct Transition {
+	pub state: [f32; FEATURE_COUNT],
+	pub reward: f32,
+	pub next_state: Option<[f32; FEATURE_COUNT]>
+}
+
+struct ReplayBuffer {
+	transitions: Vec<Transition>,
+	position: usize
+}
+
+impl ReplayBuffer {
+	fn new() -> Self {
+		ReplayBuffer {
+			transitions: Vec::with_capacity(REPLAY_BUFFER_SIZE),
+			position: 0
+		}
+	}
+
+	fn append(self: &mut Self, transition: Transition) {
+		if self.transitions.len() < REPLAY_BUFFER_SIZE {
+			self.transitions.push(transition);
+		} else {
+			self.transitions[self.position] = transition;
+		}
+
+		self.position = (self.position + 1) % REPLAY_BUFFER_SIZE;
+	}
+
+	// distinct transitions like numpy choice without replacement
+	fn sample(self: &Self, random: &mut Random) -> Vec<&Transition> {
+		let mut indices: Vec<usize> = Vec::with_capacity(BATCH_SIZE);
+
+		while indices.len() < BATCH_SIZE.min(self.transitions.len()) {
+			let index: usize = (random.next_u64() % self.transitions.len() as u64) as usize;
+
+			if !indices.contains(&index) {
+				indices.push(index);
+			}
+		}
+
+		indices.iter()
+			.map(|index: &usize| &self.transitions[*index])
+			.collect::<Vec<&Transition>>()
+	}
+}
+
+// first and second moments of one layer
+struct Moments {
+	weight_first: Vec<f32>,
+	weight_second: Vec<f32>,
+	bias_first: Vec<f32>,
+	bias_second: Vec<f32>
+}
+
+struct Adam {
+	moments: Vec<Moments>,
+	step: i32
+}
+
+impl Adam {
+	fn new(network: &Perceptron) -> Self {
+		Adam {
+			moments: network.layers.iter()
+				.map(|layer: &Layer| Moments {
+					weight_first: vec![0.0; layer.weights.len()],
+					weight_second: vec![0.0; layer.weights.len()],
+					bias_first: vec![0.0; layer.bias.len()],
+					bias_second: vec![0.0; layer.bias.len()]
+				})
+				.collect::<Vec<Moments>>(),
+			step: 0
+		}
+	}
+
+	fn update(self: &mut Self, network: &mut Perceptron, gradients: &[(Vec<f32>, Vec<f32>)]) {
+		self.step += 1;
+
+		let rate: f32 = LEARNING_RATE * (1.0 - BETA_2.powi(self.step)).sqrt() / (1.0 - BETA_1.powi(self.step));
+
+		for ((layer, moments), gradient) in network.layers.iter_mut().zip(&mut self.moments).zip(gradients) {
+			Adam::apply(&mut layer.weights, &mut moments.weight_first, &mut moments.weight_second, &gradient.0, rate);
+			Adam::apply(&mut layer.bias, &mut moments.bias_first, &mut moments.bias_second, &gradient.1, rate);
+		}
+	}
+
+	fn apply(parameters: &mut [f32], first: &mut [f32], second: &mut [f32], gradients: &[f32], rate: f32) {
+		for i in 0..parameters.len() {
+			first[i] = BETA_1 * first[i] + (1.0 - BETA_1) * gradients[i];
+			second[i] = BETA_2 * second[i] + (1.0 - BETA_2) * gradients[i] * gradients[i];
+			parameters[i] -= rate * first[i] / (second[i].sqrt() + EPSILON);
+		}
+	}
+}
+
+// mean squared error gradients of network over states and target scores
+fn get_gradients(network: &Perceptron, states: &[[f32; FEATURE_COUNT]], targets: &[f32]) -> Vec<(Vec<f32>, Vec<f32>)> {
+	let mut gradients: Vec<(Vec<f32>, Vec<f32>)> = network.layers.iter()
+		.map(|layer: &Layer| (vec![0.0; layer.weights.len()], vec![0.0; layer.bias.len()]))
+		.collect::<Vec<(Vec<f32>, Vec<f32>)>>();
+	let mut outputs: Vec<Vec<f32>> = vec![Vec::new(); network.layers.len() + 1];
+	// activation slope comes from these, activated outputs lose sign once relu turns them into -0
+	let mut pre_activations: Vec<Vec<f32>> = vec![Vec::new(); network.layers.len()];
+
+	for (state, target) in states.iter().zip(targets) {
+		outputs[0].clear();
+		outputs[0].extend_from_slice(state);
+
+		for (i, layer) in network.layers.iter().enumerate() {
+			layer.transform(&outputs[i], &mut pre_activations[i]);
+			outputs[i + 1].clear();
+			outputs[i + 1].extend_from_slice(&pre_activations[i]);
+			layer.activate(&mut outputs[i + 1]);
+		}
+
+		let mut deltas: Vec<f32> = vec![2.0 * (outputs[network.layers.len()][0] - target) / states.len() as f32];
+
+		for (i, layer) in network.layers.iter().enumerate().rev() {
+			if let Some(alpha) = layer.alpha {
+				for (delta, pre_activation) in deltas.iter_mut().zip(&pre_activations[i]) {
+					if *pre_activation < 0.0 {
+						*delta *= alpha;
+					}
+				}
+			}
+
+			let mut previous_deltas: Vec<f32> = vec![0.0; outputs[i].len()];
+			let (weight_gradients, bias_gradients): &mut (Vec<f32>, Vec<f32>) = &mut gradients[i];
+
+			for (j, input) in outputs[i].iter().enumerate() {
+				let row: usize = j * layer.bias.len();
+
+				for (k, delta) in deltas.iter().enumerate() {
+					weight_gradients[row + k] += input * delta;
+					previous_deltas[j] += layer.weights[row + k] * delta;
+				}
+			}
+
+			for (bias_gradient, delta) in bias_gradients.iter_mut().zip(&deltas) {
+				*bias_gradient += delta;
+			}
+
+			deltas = previous_deltas;
+		}
+	}
+
+	gradients
+}
+
+struct Trial {
+	previous: Perceptron,
+	baseline: f64
+}
+
+// mirrors DeepQNetworkAgent.train_from_replay and publishes weights guarded by live hit rate
+pub struct Trainer {
+	policy: Perceptron,
+	target: Perceptron,
+	// weights currently serving evictions
+	live: Perceptron,
+	optimizer: Adam,
+	buffer: ReplayBuffer,
+	random: Random,
+	step_count: u64,
+	published_step_count: u64,
+	window: (u64, u64),
+	trial: Option<Trial>
+}
+
+impl Trainer {
+	pub fn new(network: Perceptron) -> Self {
+		Trainer {
+			optimizer: Adam::new(&network),
+			target: network.clone(),
+			live: network.clone(),
+			policy: network,
+			buffer: ReplayBuffer::new(),
+			random: Random::new(),
+			step_count: 0,
+			published_step_count: 0,
+			window: (0, 0),
+			trial: None
+		}
+	}
+
+	pub fn run(self: &mut Self, cache: Arc<Mutex<Cache>>, receiver: Receiver<Transition>, interval: Duration) {
+		let mut published_at: Instant = Instant::now();
+
+		loop {
+			match receiver.recv_timeout(Duration::from_secs(1)) {
+				Ok(transition) => {
+					self.buffer.append(transition);
+
+					if self.buffer.transitions.len() > BATCH_SIZE {
+						self.step();
+					}
+				},
+				Err(RecvTimeoutError::Timeout) => {},
+				Err(RecvTimeoutError::Disconnected) => return
+			}
+
+			if published_at.elapsed() >= interval {
+				published_at = Instant::now();
+
+				if let Err(error) = self.publish(&cache) {
+					error!("{} from trainer\n", error);
+				}
+			}
+		}
+	}
+
+	fn step(self: &mut Self) {
+		let batch: Vec<&Transition> = self.buffer.sample(&mut self.random);
+		let states: Vec<[f32; FEATURE_COUNT]> = batch.iter()
+			.map(|transition: &&Transition| transition.state)
+			.collect::<Vec<[f32; FEATURE_COUNT]>>();
+		let mut buffers: (Vec<f32>, Vec<f32>) = (Vec::new(), Vec::new());
+		let targets: Vec<f32> = batch.iter()
+			.map(|transition: &&Transition| transition.reward + if let Some(next_state) = &transition.next_state {
+				GAMMA * self.target.score(next_state, &mut buffers)
+			} else {
+				0.0
+			})
+			.collect::<Vec<f32>>();
+		let gradients: Vec<(Vec<f32>, Vec<f32>)> = get_gradients(&self.policy, &states, &targets);
+
+		self.optimizer.update(&mut self.policy, &gradients);
+		self.step_count += 1;
+
+		if self.step_count.is_multiple_of(TARGET_UPDATE_FREQUENCY) {
+			self.target = self.policy.clone();
+
+			if ARGUMENT.is_verbose {
+				debug!("trainer synced target network after {} steps\n", self.step_count);
+			}
+		}
+	}
+
+	// training restarts from given weights, dropping steps and trial made on others
+	fn reset(self: &mut Self, network: Perceptron) {
+		self.policy = network.clone();
+		self.target = network.clone();
+		self.live = network;
+		self.optimizer = Adam::new(&self.policy);
+		self.published_step_count = self.step_count;
+		self.trial = None;
+	}
+
+	// lock is held throughout, so reload cannot land between reading counts and replacing weights
+	fn publish(self: &mut Self, cache: &Arc<Mutex<Cache>>) -> Result<()> {
+		let mut cache: MutexGuard<'_, Cache> = cache.lock()
+			.map_err(|error: PoisonError<MutexGuard<'_, Cache>>| error.to_string())?;
+		let (hit_count, miss_count): (u64, u64) = cache.counts();
+
+		// reloaded weights are serving now, so they become baseline instead of being overwritten
+		if let Some(network) = cache.take_reloaded_network() {
+			self.reset(network);
+			self.window = (hit_count, miss_count);
+
+			info!("trainer restarted from reloaded weights\n");
+
+			return Ok(());
+		}
+
+		let hits: u64 = hit_count - self.window.0;
+		let lookups: u64 = hits + miss_count - self.window.1;
+
+		// window keeps growing until it holds enough lookups to judge
+		if lookups < MINIMUM_WINDOW {
+			return Ok(());
+		}
+
+		let hit_rate: f64 = hits as f64 / lookups as f64;
+
+		self.window = (hit_count, miss_count);
+
+		if let Some(trial) = self.trial.take() {
+			if hit_rate + REGRESSION_TOLERANCE < trial.baseline {
+				cache.replace_network(trial.previous.clone())?;
+
+				warn!("trainer reverted weights as hit rate fell from {:.4} to {:.4}\n", trial.baseline, hit_rate);
+
+				// training restarts from weights that did not regress
+				self.reset(trial.previous);
+
+				return Ok(());
+			}
+
+			info!("trainer kept weights as hit rate went from {:.4} to {:.4}\n", trial.baseline, hit_rate);
+		}
+
+		if self.step_count == self.published_step_count {
+			return Ok(());
+		}
+
+		cache.replace_network(self.policy.clone())?;
+
+		info!("trainer published weights after {} steps\n", self.step_count);
+
+		self.published_step_count = self.step_count;
+		self.trial = Some(Trial {
+			previous: replace(&mut self.live, self.policy.clone()),
+			baseline: hit_rate
+		});
+
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::perceptron::{Layer, Perceptron};
+	use super::get_gradients;
+
+	#[test]
+	fn stops_gradient_at_dead_relu() {
+		let network: Perceptron = Perceptron {
+			layers: vec![
+				Layer {
+					weights: vec![-1.0; 4],
+					bias: vec![0.0],
+					output_count: 1,
+					alpha: Some(0.0)
+				},
+				Layer {
+					weights: vec![1.0],
+					bias: vec![0.0],
+					output_count: 1,
+					alpha: None
+				}
+			]
+		};
+		// relu turns negative pre activation into -0, which compares equal to 0
+		let gradients: Vec<(Vec<f32>, Vec<f32>)> = get_gradients(&network, &[[1.0, 2.0, 3.0, 4.0]], &[1.0]);
+
+		assert_eq!(gradients[0], (vec![0.0; 4], vec![0.0]));
+		assert_eq!(gradients[1], (vec![0.0], vec![-2.0]));
+	}
+}
\ No newline at end of file
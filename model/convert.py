@@ -1,12 +1,18 @@
 from os import listdir
 from tensorflow import TensorSpec
 from keras.models import load_model
+from onnx import save
+from onnx.helper import set_model_props
 from tf2onnx.convert import from_keras
 
 file = sorted(listdir('saves'))[-1]
 model = load_model(f'saves/{file}')
 model.output_names = []
 
-from_keras(model, [TensorSpec(model.inputs[0].shape, model.inputs[0].dtype)], opset=13, output_path='model.onnx')
+model_proto, _ = from_keras(model, [TensorSpec(model.inputs[0].shape, model.inputs[0].dtype)], opset=13)
+
+# must match FEATURE_SCHEMA_VERSION of database/src/feature.rs
+set_model_props(model_proto, {'feature_schema_version': '1'})
+save(model_proto, 'model.onnx')
 
 print(f'saved saves/{file} as model.onnx')
\ No newline at end of file
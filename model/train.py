@@ -144,6 +144,13 @@ def load_datas(path, count):
 
 	return [data.iloc[indices] for indices in array_split(range(len(data)), count)]
 
+# %%
+from numpy import load
+
+# rows logged by server with --experience: state, reward, next_state, terminal
+def load_experiences(path):
+	return [(row[0:4], row[4], None if row[9] else row[5:9]) for row in load(path)]
+
 # %%
 from time import time
 from math import trunc